@@ -2,11 +2,14 @@
 // http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
-use super::{tools::*, Options};
+use super::{
+    glyphs::{Arms, Arrowhead, Glyphs, Stroke, Transform, Weight::Light},
+    tools::*,
+    Options,
+};
 use clipboard::{ClipboardContext, ClipboardProvider};
-use core::ops::Add;
 use cursive::{
-    event::{Event, EventResult, MouseButton::*, MouseEvent::*},
+    event::{Event, EventResult, Key, MouseButton::*, MouseEvent, MouseEvent::*},
     theme::ColorStyle,
     view::{scroll::Scroller, View},
     views::ScrollView,
@@ -14,22 +17,30 @@ use cursive::{
 };
 use lazy_static::lazy_static;
 use line_drawing::Bresenham;
-use num_traits::Zero;
+use log::warn;
 use parking_lot::{Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use pathfinding::directed::astar::astar;
 use std::{
     cmp::{max, min},
+    collections::{BTreeMap, BTreeSet, HashMap},
+    env,
     error::Error,
-    f64::consts::SQRT_2,
+    ffi::OsString,
     fs::{self, File, OpenOptions},
     io::{self, BufRead, BufReader, ErrorKind, Read, Write},
     iter, mem,
     path::{Path, PathBuf},
+    process,
     rc::Rc,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::{Duration, Instant},
 };
 
 pub(crate) const CONSUMED: Option<EventResult> = Some(EventResult::Consumed(None));
 
+/// How long status messages are displayed for.
+pub(crate) const STATUS_TTL: Duration = Duration::from_secs(3);
+
 macro_rules! intercept_scrollbar {
     ($ctx:expr, $event:expr) => {{
         lazy_static! {
@@ -156,6 +167,34 @@ impl<'a> EditorCtx<'a> {
         intercept_scrollbar!(self, event);
         intercept_pan!(self, event);
 
+        self.track_pointer(event);
+        self.with_tool(event)
+            .or_else(|| self.on_cursor_event(event))
+    }
+
+    /// If `event` is a mouse event, record its position on the canvas, as well as where
+    /// the current left button drag (if any) started.
+    fn track_pointer(&mut self, event: &Event) {
+        let (pos, event) = match self.relativize(event) {
+            Event::Mouse {
+                position, event, ..
+            } => (position, event),
+
+            _ => return,
+        };
+
+        let mut editor = self.0.get_inner_mut().write();
+        editor.pointer = Some(pos);
+
+        match event {
+            Press(Left) => editor.drag_src = Some(pos),
+            Hold(Left) => {}
+            _ => editor.drag_src = None,
+        }
+    }
+
+    /// Pass `event` to the active tool.
+    fn with_tool(&mut self, event: &Event) -> Option<EventResult> {
         let mut tool = self.0.get_inner_mut().write().active_tool.take().unwrap();
         let res = tool.on_event(self, event);
         self.0.get_inner_mut().write().active_tool = Some(tool);
@@ -163,6 +202,65 @@ impl<'a> EditorCtx<'a> {
         res
     }
 
+    /// Handles an event that the active tool ignored using the keyboard cursor, which
+    /// drives the active tool with synthetic mouse events.
+    fn on_cursor_event(&mut self, event: &Event) -> Option<EventResult> {
+        let (cursor, anchored) = {
+            let editor = self.0.get_inner_mut().read();
+            (editor.kb_cursor, editor.kb_anchored)
+        };
+
+        if let Event::Char('K') = event {
+            let tl = self.0.content_viewport().top_left();
+            let mut editor = self.0.get_inner_mut().write();
+            editor.kb_cursor = cursor.xor(Some(tl));
+            editor.kb_anchored = false;
+            return CONSUMED;
+        }
+
+        let mut pos = cursor?;
+
+        match event {
+            Event::Key(Key::Up) | Event::Char('k') => pos.y = pos.y.saturating_sub(1),
+            Event::Key(Key::Down) | Event::Char('j') => pos.y += 1,
+            Event::Key(Key::Left) | Event::Char('h') => pos.x = pos.x.saturating_sub(1),
+            Event::Key(Key::Right) | Event::Char('l') => pos.x += 1,
+
+            Event::Char(' ') | Event::Key(Key::Enter) => {
+                self.0.get_inner_mut().write().kb_anchored = !anchored;
+                let event = if anchored { Release(Left) } else { Press(Left) };
+                return self.synthesize(pos, event).or(CONSUMED);
+            }
+
+            _ => return None,
+        }
+
+        self.0.get_inner_mut().write().kb_cursor = Some(pos);
+        self.scroll_to(pos, 1, 1);
+
+        if anchored {
+            return self.synthesize(pos, Hold(Left)).or(CONSUMED);
+        }
+
+        CONSUMED
+    }
+
+    /// Pass a mouse event at `pos` on the canvas to the active tool.
+    fn synthesize(&mut self, pos: Vec2, event: MouseEvent) -> Option<EventResult> {
+        // make sure `pos` is in the viewport, otherwise it can't be relativized
+        self.scroll_to(pos, 1, 1);
+
+        let tl = self.0.content_viewport().top_left();
+        let event = Event::Mouse {
+            offset: Vec2::zero(),
+            position: pos - tl,
+            event,
+        };
+
+        self.track_pointer(&event);
+        self.with_tool(&event)
+    }
+
     /// Returns `true` if `pos` is located on a scrollbar.
     fn on_scrollbar(&self, offset: Vec2, pos: Vec2) -> bool {
         let core = self.0.get_scroller();
@@ -225,16 +323,16 @@ impl<'a> EditorCtx<'a> {
         }
     }
 
-    /// Modify the edit buffer using `render`, flushing any changes and saving a snapshot
-    /// of the buffer's prior state in the editor's undo history.
-    pub(crate) fn clobber<R: FnOnce(&mut Buffer)>(&mut self, render: R) {
+    /// Modify the edit buffer using `render`, flushing any changes and recording them in
+    /// the editor's undo history. Returns `false` if nothing changed.
+    pub(crate) fn clobber<R: FnOnce(&mut Buffer)>(&mut self, render: R) -> bool {
         let mut editor = self.0.get_inner_mut().write();
 
-        editor.with_snapshot(|ed| {
+        editor.with_history(|ed| {
             render(&mut ed.buffer);
             ed.buffer.flush_edits();
             ed.buffer.drop_cursor();
-        });
+        })
     }
 
     /// Modify the edit buffer using `render`, without flushing any changes.
@@ -243,6 +341,27 @@ impl<'a> EditorCtx<'a> {
         editor.buffer.discard_edits();
         render(&mut editor.buffer);
     }
+
+    /// Returns the last known position of the mouse or keyboard cursor on the canvas.
+    pub(crate) fn pointer(&mut self) -> Option<Vec2> {
+        self.0.get_inner_mut().read().pointer()
+    }
+
+    /// Copy `cells` into the editor's register and the system clipboard.
+    pub(crate) fn copy(&mut self, cells: Vec<Cell>) {
+        self.0.get_inner_mut().write().copy(cells);
+    }
+
+    /// Returns the cells to paste. See `Editor::paste`.
+    pub(crate) fn paste(&mut self) -> Vec<Cell> {
+        self.0.get_inner_mut().write().paste()
+    }
+
+    /// Mutate the editor's options with `apply`. Options aren't reloaded into the tool
+    /// that is handling the current event.
+    pub(crate) fn mut_opts<F: FnOnce(&mut Options)>(&mut self, apply: F) {
+        self.0.get_inner_mut().write().mut_opts(apply);
+    }
 }
 
 #[derive(Clone)]
@@ -263,6 +382,11 @@ impl View for EditorView {
                 Char::Cursor(Cell { pos, c }) => cursor(p, pos, c),
             }
         }
+
+        let editor = self.read();
+        if let Some(pos) = editor.kb_cursor {
+            cursor(p, pos, editor.buffer.peek(pos).unwrap_or(SP));
+        }
     }
 
     fn required_size(&mut self, size: Vec2) -> Vec2 {
@@ -289,11 +413,11 @@ impl EditorView {
         }
     }
 
-    pub(crate) fn read(&self) -> RwLockReadGuard<Editor> {
+    pub(crate) fn read(&self) -> RwLockReadGuard<'_, Editor> {
         self.inner.read()
     }
 
-    pub(crate) fn write(&self) -> RwLockWriteGuard<Editor> {
+    pub(crate) fn write(&self) -> RwLockWriteGuard<'_, Editor> {
         self.inner.write()
     }
 }
@@ -301,13 +425,22 @@ impl EditorView {
 pub(crate) struct Editor {
     opts: Options,
     buffer: Buffer,
-    lsave: Buffer,
-    dirty: bool,
-    undo_history: Vec<Buffer>,
-    redo_history: Vec<Buffer>,
+    saved: Option<usize>,
+    undo_history: Vec<Delta>,
+    redo_history: Vec<Delta>,
     active_tool: Option<Box<dyn Tool>>,
+    kb_cursor: Option<Vec2>,
+    kb_anchored: bool,
+    pointer: Option<Vec2>,
+    drag_src: Option<Vec2>,
     canvas: Vec2,
     rendered: String,
+    recovery: Option<PathBuf>,
+    offered_recovery: Option<PathBuf>,
+    autosaved: bool,
+    status: Option<(String, Instant)>,
+    register: Vec<Cell>,
+    clipped: Option<String>,
 }
 
 fn print_styled(style: ColorStyle) -> impl FnMut(&Printer<'_, '_>, Vec2, char) {
@@ -328,19 +461,30 @@ impl Editor {
         let mut editor = Self {
             opts,
             buffer: Buffer::default(),
-            lsave: Buffer::default(),
-            dirty: false,
+            saved: Some(0),
             undo_history: vec![],
             redo_history: vec![],
             active_tool: Some(Box::new(tool)),
+            kb_cursor: None,
+            kb_anchored: false,
+            pointer: None,
+            drag_src: None,
             canvas: Vec2::new(0, 0),
             rendered: String::default(),
+            recovery: None,
+            offered_recovery: None,
+            autosaved: false,
+            status: None,
+            register: vec![],
+            clipped: None,
         };
 
         if let Some(path) = file {
-            editor.open_file(path)?;
+            editor.load_file(path)?;
         }
 
+        editor.recovery = editor.find_recovery();
+
         Ok(editor)
     }
 
@@ -354,7 +498,7 @@ impl Editor {
 
     /// Returns `true` if the buffer has been modified since the last save.
     pub(crate) fn is_dirty(&self) -> bool {
-        self.dirty
+        self.saved != Some(self.undo_history.len())
     }
 
     /// Set the active tool.
@@ -363,6 +507,7 @@ impl Editor {
         self.buffer.drop_cursor();
         tool.load_opts(&self.opts);
         self.active_tool = Some(Box::new(tool));
+        self.kb_anchored = false;
     }
 
     /// Returns the active tool as a human readable string.
@@ -370,6 +515,90 @@ impl Editor {
         format!("({})", self.active_tool.as_ref().unwrap())
     }
 
+    /// Returns `true` if Line and Arrow tools prompt for a label.
+    pub(crate) fn line_labels(&self) -> bool {
+        self.opts.line_labels
+    }
+
+    /// Draw a table of `rows` by `cols` cells over `area`. Returns the inside of each cell,
+    /// in row major order.
+    pub(crate) fn draw_table(
+        &mut self,
+        stroke: Stroke,
+        area: Rect,
+        rows: usize,
+        cols: usize,
+    ) -> Vec<Rect> {
+        let mut cells = vec![];
+
+        self.with_history(|ed| {
+            cells = ed.buffer.draw_table(stroke, area, rows, cols);
+            ed.buffer.flush_edits();
+        });
+
+        cells
+    }
+
+    /// Set the cursor position to `pos`, until the next edit.
+    pub(crate) fn set_cursor(&mut self, pos: Vec2) {
+        self.buffer.set_cursor(pos);
+    }
+
+    /// Label the line through each of `points` with `label`. If `amend` is set, this is
+    /// part of the last undo step, which must be the one that drew the line.
+    pub(crate) fn label_line(&mut self, points: &[Vec2], label: &str, amend: bool) {
+        let draw = |ed: &mut Self| {
+            ed.buffer.draw_label(points, label);
+            ed.buffer.flush_edits();
+        };
+
+        if amend {
+            self.amend_history(draw);
+        } else {
+            self.with_history(draw);
+        }
+    }
+
+    /// Returns `true` if a backup of the prior version of the file is kept on save.
+    pub(crate) fn backup(&self) -> bool {
+        self.opts.backup
+    }
+
+    /// Set the status message, which is displayed for `STATUS_TTL`.
+    pub(crate) fn set_status<S: Into<String>>(&mut self, msg: S) {
+        self.status = Some((msg.into(), Instant::now()));
+    }
+
+    /// Returns the status message, if it hasn't expired yet.
+    pub(crate) fn status(&self) -> Option<&str> {
+        self.status
+            .as_ref()
+            .filter(|(_, at)| at.elapsed() < STATUS_TTL)
+            .map(|(msg, _)| msg.as_str())
+    }
+
+    /// Returns the last known position of the mouse or keyboard cursor on the canvas.
+    pub(crate) fn pointer(&self) -> Option<Vec2> {
+        self.kb_cursor.or(self.pointer)
+    }
+
+    /// Returns the dimensions of the area covered by the current drag, if any.
+    pub(crate) fn drag_size(&self) -> Option<Vec2> {
+        let (src, dst) = (self.drag_src?, self.pointer?);
+        Some(Rect::from_corners(src, dst).size())
+    }
+
+    /// Returns the dimensions of the buffer's content.
+    pub(crate) fn bounds(&self) -> Vec2 {
+        self.buffer.bounds()
+    }
+
+    /// Returns the committed content of the buffer.
+    #[cfg(test)]
+    pub(crate) fn contents(&self) -> String {
+        self.buffer.iter("").collect()
+    }
+
     /// Returns the current save path.
     pub(crate) fn path(&self) -> Option<&PathBuf> {
         self.opts.file.as_ref()
@@ -377,13 +606,19 @@ impl Editor {
 
     /// Clear all buffer state and begin a blank diagram.
     pub(crate) fn clear(&mut self) {
+        self.discard_recovery();
+        self.reset();
+    }
+
+    /// Reset all buffer state to that of a blank diagram.
+    fn reset(&mut self) {
         self.opts.file = None;
         self.buffer.clear();
-        self.lsave.clear();
-        self.dirty = false;
+        self.saved = Some(0);
         self.undo_history.clear();
         self.redo_history.clear();
         self.canvas = Vec2::new(0, 0);
+        self.autosaved = false;
     }
 
     /// Open the file at `path`, discarding any unsaved changes to the current file, if
@@ -391,21 +626,31 @@ impl Editor {
     ///
     /// No modifications have been performed if this returns `Err(_)`.
     pub(crate) fn open_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
-        let buffer = OpenOptions::new()
-            .read(true)
-            .open(path.as_ref())
-            .and_then(Buffer::read_from);
+        let recovery = self.recovery_path();
+        self.load_file(path)?;
+        remove_recovery(&recovery);
+
+        if let Some(offered) = self.offered_recovery.take() {
+            remove_recovery(&offered);
+        }
 
-        let buffer = match buffer {
+        Ok(())
+    }
+
+    /// Open the file at `path`, without discarding the recovery file of the current one.
+    fn load_file<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let content = match fs::read(path.as_ref()) {
             Err(e) if e.kind() == ErrorKind::NotFound => None,
             r => Some(r?),
         };
 
-        self.clear();
+        let buffer = content.as_deref().map(Buffer::read_from).transpose()?;
+
+        self.reset();
         self.opts.file = Some(path.as_ref().into());
-        if let Some(buf) = buffer {
-            self.lsave = buf.clone();
+        if let (Some(buf), Some(content)) = (buffer, content) {
             self.buffer = buf;
+            self.load_history(path.as_ref(), &content);
         }
 
         Ok(())
@@ -417,20 +662,27 @@ impl Editor {
     /// configured for saving.
     ///
     /// If the configured save path does not exist, this will recursively create it.
+    ///
+    /// The file is replaced atomically, so it is never left partially written. If backups
+    /// are enabled, its prior content is copied to a `~` suffixed file first.
     pub(crate) fn save(&mut self) -> io::Result<bool> {
-        if let Some(path) = self.path() {
+        if let Some(path) = self.path().cloned() {
             path.parent().map(fs::create_dir_all).transpose()?;
 
-            let file = OpenOptions::new()
-                .read(false)
-                .write(true)
-                .create(true)
-                .truncate(true)
-                .open(path)?;
+            if self.opts.backup {
+                match fs::copy(&path, sibling_path(&path, "", "~")) {
+                    Err(e) if e.kind() == ErrorKind::NotFound => {}
+                    r => drop(r?),
+                }
+            }
+
+            write_atomic(&path, |file| self.render_to_file(file))?;
+            self.saved = Some(self.undo_history.len());
+            self.discard_recovery();
 
-            self.render_to_file(file)?;
-            self.lsave = self.buffer.clone();
-            self.dirty = false;
+            if let Err(e) = self.store_history(&path) {
+                warn!("failed to store undo history for {:?}: {}", path, e);
+            }
         }
 
         Ok(self.path().is_some())
@@ -439,8 +691,10 @@ impl Editor {
     /// Save the current buffer contents to the file at `path`, and setting that as the
     /// new path for future calls to `save`.
     pub(crate) fn save_as<P: AsRef<Path>>(&mut self, path: P) -> io::Result<()> {
+        let recovery = self.recovery_path();
         self.opts.file = Some(path.as_ref().into());
         self.save()?;
+        remove_recovery(&recovery);
 
         Ok(())
     }
@@ -449,7 +703,7 @@ impl Editor {
     fn render_to_file(&mut self, mut file: File) -> io::Result<()> {
         self.canvas = Vec2::new(0, 0);
 
-        self.with_snapshot(|ed| {
+        self.with_history(|ed| {
             if ed.opts.strip_margin_ws {
                 ed.buffer.strip_margin_whitespace();
             } else if !ed.opts.keep_trailing_ws {
@@ -467,11 +721,202 @@ impl Editor {
         Ok(())
     }
 
+    /// Store the undo history in a file alongside `path`, tagged with a hash of what
+    /// was last rendered to it.
+    fn store_history(&self, path: &Path) -> io::Result<()> {
+        let mut out = vec![];
+        let hash = fnv1a(self.rendered.as_bytes());
+        writeln!(out, "{} {} {:016x}", HISTORY_MAGIC, HISTORY_VERSION, hash)?;
+
+        for history in &[&self.undo_history, &self.redo_history] {
+            writeln!(out, "{}", history.len())?;
+            for delta in history.iter() {
+                delta.write_to(&mut out)?;
+            }
+        }
+
+        write_atomic(&history_path(path), |mut file| {
+            file.write_all(&out)?;
+            file.sync_all()
+        })
+    }
+
+    /// Load the undo history stored alongside `path`, if one exists and was stored
+    /// when the file contained `content`.
+    fn load_history(&mut self, path: &Path, content: &[u8]) -> Option<()> {
+        let raw = fs::read_to_string(history_path(path)).ok()?;
+        let mut tokens = raw.split_whitespace();
+
+        if tokens.next()? != HISTORY_MAGIC || tokens.next()? != HISTORY_VERSION {
+            return None;
+        }
+
+        if u64::from_str_radix(tokens.next()?, 16).ok()? != fnv1a(content) {
+            return None;
+        }
+
+        let mut histories = [vec![], vec![]];
+        for history in histories.iter_mut() {
+            for _ in 0..tokens.next()?.parse::<usize>().ok()? {
+                history.push(Delta::read_from(&mut tokens)?);
+            }
+
+            // make sure the history can actually be replayed from the current state
+            let mut buf = self.buffer.clone();
+            for delta in history.iter().rev() {
+                if !buf.can_apply(delta) {
+                    return None;
+                }
+                buf.apply(delta);
+            }
+        }
+
+        let [undo, redo] = histories;
+        self.saved = Some(undo.len());
+        self.undo_history = undo;
+        self.redo_history = redo;
+
+        Some(())
+    }
+
+    /// Returns the path of the file that unsaved changes to the buffer are autosaved to.
+    fn recovery_path(&self) -> PathBuf {
+        let name = match self.path() {
+            Some(path) => {
+                let abs = env::current_dir().map_or_else(|_| path.clone(), |d| d.join(path));
+                let name = path.file_name().unwrap_or_default().to_string_lossy();
+                format!("{:016x}-{}", fnv1a(abs.to_string_lossy().as_bytes()), name)
+            }
+
+            // unnamed buffers of concurrent instances shouldn't share a recovery file
+            None => format!("{}{}", SCRATCH_PREFIX, process::id()),
+        };
+
+        self.recovery_dir().join(name)
+    }
+
+    /// Returns the directory that recovery files are autosaved to.
+    fn recovery_dir(&self) -> PathBuf {
+        self.opts
+            .recovery_dir
+            .clone()
+            .unwrap_or_else(default_recovery_dir)
+    }
+
+    /// Returns the path of the recovery file for the buffer, if there is one that is
+    /// newer than the file it was autosaved from.
+    ///
+    /// For an unnamed buffer, this is the newest one left behind by an unnamed buffer of
+    /// an instance that is no longer running.
+    fn find_recovery(&self) -> Option<PathBuf> {
+        if self.path().is_none() {
+            return find_orphaned_scratch(&self.recovery_dir());
+        }
+
+        let path = self.recovery_path();
+        let autosaved = fs::metadata(&path).and_then(|m| m.modified()).ok()?;
+
+        match self
+            .path()
+            .map(|p| fs::metadata(p).and_then(|m| m.modified()))
+        {
+            Some(Ok(saved)) if saved >= autosaved => None,
+            _ => Some(path),
+        }
+    }
+
+    /// Returns the path of the recovery file found when the editor was opened, if there
+    /// was one. Subsequent calls return `None`.
+    pub(crate) fn take_recovery(&mut self) -> Option<PathBuf> {
+        self.offered_recovery = self.recovery.take();
+        self.offered_recovery.clone()
+    }
+
+    /// Replace the buffer's content with that of the recovery file at `path`.
+    pub(crate) fn recover(&mut self, path: &Path) -> io::Result<()> {
+        let buf = Buffer::read_from(File::open(path)?)?;
+        self.with_history(|ed| ed.buffer.replace(buf));
+        Ok(())
+    }
+
+    /// Write the buffer to its recovery file if it has unsaved changes that haven't been
+    /// autosaved yet.
+    pub(crate) fn autosave(&mut self) -> io::Result<()> {
+        if !self.is_dirty() || self.autosaved {
+            return Ok(());
+        }
+
+        let path = self.recovery_path();
+        path.parent().map(fs::create_dir_all).transpose()?;
+
+        let content: String = self.buffer.iter("").collect();
+        write_atomic(&path, |mut file| {
+            file.write_all(content.as_bytes())?;
+            file.sync_all()
+        })?;
+
+        self.autosaved = true;
+        Ok(())
+    }
+
+    /// Remove the buffer's recovery file, and the one that was offered when the editor
+    /// was opened, if they exist.
+    pub(crate) fn discard_recovery(&mut self) {
+        remove_recovery(&self.recovery_path());
+
+        if let Some(offered) = self.offered_recovery.take() {
+            remove_recovery(&offered);
+        }
+    }
+
+    /// Copy `cells`, which are relative to the top left of the copied area, into the
+    /// register. They are also copied into the system clipboard as text, if it is
+    /// available.
+    pub(crate) fn copy(&mut self, cells: Vec<Cell>) {
+        let text = cells_to_text(&cells);
+        self.register = cells;
+        self.clipped = None;
+
+        match self
+            .system_clipboard()
+            .and_then(|mut ctx| ctx.set_contents(text.clone()))
+        {
+            Ok(()) => self.clipped = Some(text),
+            Err(e) => warn!("failed to copy to clipboard: {}", e),
+        }
+    }
+
+    /// Returns the cells to paste, relative to the top left of the pasted area.
+    ///
+    /// This is the content of the system clipboard if it was changed by something else
+    /// since the last copy, and the content of the register otherwise.
+    pub(crate) fn paste(&mut self) -> Vec<Cell> {
+        let text = self
+            .system_clipboard()
+            .and_then(|mut ctx| ctx.get_contents());
+
+        match text {
+            Ok(text) if !text.is_empty() && Some(&text) != self.clipped.as_ref() => {
+                cells_from_text(&text)
+            }
+            _ => self.register.clone(),
+        }
+    }
+
+    /// Returns a handle to the system clipboard, unless it is disabled.
+    fn system_clipboard(&self) -> Result<ClipboardContext, Box<dyn Error>> {
+        if self.opts.no_clipboard {
+            return Err("the system clipboard is disabled".into());
+        }
+
+        ClipboardContext::new()
+    }
+
     /// Render to the clipboard, prefixing all lines with `prefix`.
     ///
     /// Trims all margins in the output without changing the buffer's state.
     pub(crate) fn render_to_clipboard(&self, prefix: &str) -> Result<(), Box<dyn Error>> {
-        let mut ctx = ClipboardContext::new()?;
+        let mut ctx = self.system_clipboard()?;
 
         let mut buf = self.buffer.clone();
         buf.strip_margin_whitespace();
@@ -488,66 +933,253 @@ impl Editor {
 
     /// Trim all whitespace from margins.
     pub(crate) fn trim_margins(&mut self) {
-        self.with_snapshot(|ed| {
+        self.with_history(|ed| {
             ed.canvas = Vec2::new(0, 0);
             ed.buffer.strip_margin_whitespace();
         });
     }
 
-    /// Take a snapshot of the buffer, discard any pending edits, and run `apply`. If
-    /// the buffer was modified, mark it as dirty. Otherwise, remove the snapshot.
+    /// Discard any pending edits and run `apply`. If the buffer was modified, record the
+    /// change in the undo history.
     ///
     /// Use this function to execute any buffer modification that should be saved in the
-    /// undo history.
-    fn with_snapshot<F: FnOnce(&mut Self)>(&mut self, apply: F) {
-        let snapshot = self.buffer.snapshot();
-        self.undo_history.push(snapshot);
+    /// undo history. Returns `false` if the buffer wasn't modified.
+    fn with_history<F: FnOnce(&mut Self)>(&mut self, apply: F) -> bool {
         self.buffer.discard_edits();
 
         apply(self);
 
-        if self.undo_history.last().unwrap() == &self.buffer {
-            self.undo_history.pop();
-        } else {
-            self.dirty = true;
+        let delta = self.buffer.take_delta();
+        if delta.is_empty() {
+            return false;
+        }
+
+        // the saved state can't be reached anymore if it was undone
+        if self.saved > Some(self.undo_history.len()) {
+            self.saved = None;
+        }
+
+        self.undo_history.push(delta.inverse());
+        self.redo_history.clear();
+        self.autosaved = false;
+
+        true
+    }
+
+    /// Like `with_history`, but folds the modification into the last step of the undo
+    /// history instead of adding a new one.
+    fn amend_history<F: FnOnce(&mut Self)>(&mut self, apply: F) {
+        let last = self.undo_history.pop();
+        if let Some(delta) = &last {
+            self.buffer.discard_edits();
+            self.buffer.apply(delta);
         }
+
+        let redo = last.map(Delta::inverse);
+        self.with_history(|ed| {
+            if let Some(delta) = &redo {
+                ed.buffer.replay(delta);
+            }
+            apply(ed);
+        });
     }
 
     /// Undo the last buffer modification.
     ///
     /// Returns `false` if there was nothing to undo.
     pub(crate) fn undo(&mut self) -> bool {
-        let undone = self
-            .undo_history
-            .pop()
-            .map(|buffer| mem::replace(&mut self.buffer, buffer))
-            .map(|buffer| self.redo_history.push(buffer))
-            .is_some();
-
-        if undone {
-            self.dirty = self.buffer != self.lsave;
-        }
-
-        undone
+        self.autosaved = false;
+        Self::step_history(
+            &mut self.buffer,
+            &mut self.undo_history,
+            &mut self.redo_history,
+        )
     }
 
     /// Redo the last undone buffer modification.
     ///
     /// Returns `false` if there was nothing to redo.
     pub(crate) fn redo(&mut self) -> bool {
-        let redone = self
-            .redo_history
-            .pop()
-            .map(|buffer| mem::replace(&mut self.buffer, buffer))
-            .map(|buffer| self.undo_history.push(buffer))
-            .is_some();
+        self.autosaved = false;
+        Self::step_history(
+            &mut self.buffer,
+            &mut self.redo_history,
+            &mut self.undo_history,
+        )
+    }
+
+    /// Apply the last delta in `from` to `buffer`, moving its inverse to `to`.
+    fn step_history(buffer: &mut Buffer, from: &mut Vec<Delta>, to: &mut Vec<Delta>) -> bool {
+        let delta = match from.pop() {
+            Some(delta) => delta,
+            None => return false,
+        };
+
+        buffer.discard_edits();
+        buffer.apply(&delta);
+        to.push(delta.inverse());
+
+        true
+    }
+}
+
+const HISTORY_MAGIC: &str = "askii-undo";
+const HISTORY_VERSION: &str = "1";
+
+/// Returns the directory that recovery files are autosaved to, unless configured
+/// otherwise.
+fn default_recovery_dir() -> PathBuf {
+    env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .unwrap_or_else(env::temp_dir)
+        .join("askii")
+        .join("recovery")
+}
+
+/// Prefix of the names of recovery files of unnamed buffers, which are followed by the
+/// id of the process that wrote them.
+const SCRATCH_PREFIX: &str = "scratch-";
+
+/// Returns the newest recovery file in `dir` of an unnamed buffer whose instance is no
+/// longer running, if there is one.
+fn find_orphaned_scratch(dir: &Path) -> Option<PathBuf> {
+    fs::read_dir(dir)
+        .ok()?
+        .filter_map(Result::ok)
+        .filter(|entry| {
+            let name = entry.file_name();
+            let pid = name.to_str().and_then(|n| n.strip_prefix(SCRATCH_PREFIX));
+            pid.and_then(|pid| pid.parse().ok())
+                .is_some_and(|pid| !process_alive(pid))
+        })
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .max()
+        .map(|(_, path)| path)
+}
+
+/// Returns `true` if the process `pid` is running. Where that can't be determined, it
+/// is assumed to be, so that its recovery file is left alone.
+#[cfg(unix)]
+fn process_alive(pid: u32) -> bool {
+    use std::convert::TryFrom;
+
+    // no process can have an id beyond what `pid_t` holds
+    let pid = match libc::pid_t::try_from(pid) {
+        Ok(pid) => pid,
+        Err(_) => return false,
+    };
+
+    // signal 0 only checks whether the process could be signalled, and it exists if
+    // permission to do so is all that's missing
+    let signalled = unsafe { libc::kill(pid, 0) } == 0;
+    signalled || io::Error::last_os_error().raw_os_error() == Some(libc::EPERM)
+}
+
+/// Returns `true` if the process `pid` is running. Where that can't be determined, it
+/// is assumed to be, so that its recovery file is left alone.
+#[cfg(not(unix))]
+fn process_alive(_: u32) -> bool {
+    true
+}
+
+/// Remove the recovery file at `path`, if it exists.
+fn remove_recovery(path: &Path) {
+    if let Err(e) = fs::remove_file(path) {
+        if e.kind() != ErrorKind::NotFound {
+            warn!("failed to remove recovery file {:?}: {}", path, e);
+        }
+    }
+}
+
+/// Returns the path of the file that stores undo history for `path`.
+fn history_path(path: &Path) -> PathBuf {
+    sibling_path(path, ".", ".askii-undo")
+}
+
+/// Returns the path of a file in the same directory as `path`, with `prefix` and
+/// `suffix` added to its name.
+fn sibling_path(path: &Path, prefix: &str, suffix: &str) -> PathBuf {
+    let mut name = OsString::from(prefix);
+    name.push(path.file_name().unwrap_or_default());
+    name.push(suffix);
+    path.with_file_name(name)
+}
+
+/// Replace the file at `path` with whatever `write` writes, which is responsible for
+/// syncing the file to disk. This writes to a temporary file in the same directory and
+/// renames it over `path`, so that it is never left in a partially written state.
+///
+/// If `path` is a symlink, the file it points to is replaced instead of the link.
+fn write_atomic<F>(path: &Path, write: F) -> io::Result<()>
+where
+    F: FnOnce(File) -> io::Result<()>,
+{
+    let path = &resolve_links(path);
+    let (tmp, file) = create_temp(path)?;
+
+    let replaced = (|| {
+        if let Ok(meta) = fs::metadata(path) {
+            file.set_permissions(meta.permissions())?;
+        }
+        write(file)?;
+        fs::rename(&tmp, path)
+    })();
 
-        if redone {
-            self.dirty = self.buffer != self.lsave;
+    if let Err(e) = replaced {
+        let _ = fs::remove_file(&tmp);
+        return Err(e);
+    }
+
+    // make sure the rename itself is durable
+    #[cfg(unix)]
+    {
+        let dir = path.parent().filter(|dir| dir != &Path::new(""));
+        File::open(dir.unwrap_or_else(|| Path::new(".")))?.sync_all()?;
+    }
+
+    Ok(())
+}
+
+/// Create a new temporary file next to `path`, returning its path along with it. The
+/// name is unique to this process and call, and an existing file is never reused, so
+/// that concurrent saves of the same file can't write into each other's.
+fn create_temp(path: &Path) -> io::Result<(PathBuf, File)> {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+    loop {
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let suffix = format!(".{}-{}.askii-tmp", process::id(), n);
+        let tmp = sibling_path(path, ".", &suffix);
+
+        match OpenOptions::new().write(true).create_new(true).open(&tmp) {
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            r => return r.map(|file| (tmp, file)),
         }
+    }
+}
 
-        redone
+/// Returns the path that `path` ends up at after following any symlinks. Unlike
+/// `fs::canonicalize`, the target doesn't need to exist yet.
+fn resolve_links(path: &Path) -> PathBuf {
+    let mut path = path.to_path_buf();
+
+    // give up on a cycle of links, just like the OS would
+    for _ in 0..40 {
+        match fs::read_link(&path) {
+            Ok(target) => path = path.parent().unwrap_or(&path).join(target),
+            Err(_) => break,
+        }
     }
+
+    path
+}
+
+/// Returns the 64 bit FNV-1a hash of `bytes`.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3)
+    })
 }
 
 pub(crate) const SP: char = ' ';
@@ -558,10 +1190,10 @@ pub(crate) const GAID: char = '\\';
 pub(crate) const PLUS: char = '+';
 pub(crate) const CURS: char = '_';
 
-const N: char = '^';
-const S: char = 'v';
-const W: char = '<';
-const E: char = '>';
+const N: Arms = Arms::N;
+const S: Arms = Arms::S;
+const W: Arms = Arms::W;
+const E: Arms = Arms::E;
 
 const S_N: (isize, isize) = (0, -1);
 const S_E: (isize, isize) = (1, 0);
@@ -573,6 +1205,100 @@ pub(crate) struct Buffer {
     chars: Vec<Vec<char>>,
     edits: Vec<Cell>,
     cursor: Option<Vec2>,
+    journal: Journal,
+    route: Option<Route>,
+}
+
+/// The last path found by `Buffer::draw_path`, which remains valid until the committed
+/// content changes.
+#[derive(Clone, PartialEq, Eq)]
+struct Route {
+    src: Vec2,
+    dst: Vec2,
+    orthogonal: bool,
+    path: Vec<Vec2>,
+}
+
+/// The prior state of each line modified since the last call to `Buffer::take_delta`.
+#[derive(Clone, Default, PartialEq, Eq)]
+struct Journal {
+    len: Option<usize>,
+    lines: BTreeMap<usize, Vec<char>>,
+}
+
+/// A reversible modification of a buffer's content.
+#[derive(Clone, Default, PartialEq, Eq)]
+struct Delta {
+    /// The number of lines before and after.
+    len: (usize, usize),
+    /// The index and width before and after of lines that changed width.
+    widths: Vec<(usize, usize, usize)>,
+    /// The position and content before and after of cells that changed.
+    cells: Vec<(Vec2, char, char)>,
+}
+
+impl Delta {
+    fn write_to<W: Write>(&self, mut w: W) -> io::Result<()> {
+        let (before, after) = self.len;
+        writeln!(
+            w,
+            "{} {} {} {}",
+            before,
+            after,
+            self.widths.len(),
+            self.cells.len()
+        )?;
+
+        for (y, before, after) in &self.widths {
+            writeln!(w, "{} {} {}", y, before, after)?;
+        }
+
+        for (Vec2 { x, y }, before, after) in &self.cells {
+            writeln!(w, "{} {} {} {}", x, y, *before as u32, *after as u32)?;
+        }
+
+        Ok(())
+    }
+
+    fn read_from<'a, I: Iterator<Item = &'a str>>(tokens: &mut I) -> Option<Self> {
+        let mut num = || tokens.next()?.parse::<usize>().ok();
+        let mut delta = Self {
+            len: (num()?, num()?),
+            ..Self::default()
+        };
+
+        let (widths, cells) = (num()?, num()?);
+
+        for _ in 0..widths {
+            delta.widths.push((num()?, num()?, num()?));
+        }
+
+        for _ in 0..cells {
+            let pos = Vec2::new(num()?, num()?);
+            let (before, after) = (num()? as u32, num()? as u32);
+            delta
+                .cells
+                .push((pos, char::from_u32(before)?, char::from_u32(after)?));
+        }
+
+        Some(delta)
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len.0 == self.len.1 && self.widths.is_empty() && self.cells.is_empty()
+    }
+
+    /// Returns a delta that reverts this one.
+    fn inverse(mut self) -> Self {
+        self.len = (self.len.1, self.len.0);
+        for (_, before, after) in self.widths.iter_mut() {
+            mem::swap(before, after);
+        }
+        for (_, before, after) in self.cells.iter_mut() {
+            mem::swap(before, after);
+        }
+        self
+    }
 }
 
 #[derive(Copy, Clone, PartialEq, Eq)]
@@ -598,7 +1324,114 @@ impl Cell {
         self.pos = self.pos.saturating_add(by);
         self
     }
-}
+
+    /// Apply `t` to this cell, which is relative to the top left of an area of `size`.
+    pub(crate) fn transform(self, t: Transform, size: Vec2) -> Self {
+        let Vec2 { x, y } = self.pos;
+
+        let pos = match t {
+            Transform::FlipH => Vec2::new(size.x - 1 - x, y),
+            Transform::FlipV => Vec2::new(x, size.y - 1 - y),
+            Transform::Rotate => Vec2::new(size.y - 1 - y, x),
+        };
+
+        Self {
+            pos,
+            c: t.glyph(self.c),
+        }
+    }
+}
+
+/// Apply `t` to `cells`, which are relative to the top left of an area of `size`.
+///
+/// Glyphs are remapped with their neighbors in mind. Ascii dashed lines also keep their
+/// look when rotated: horizontal ones are spaced out while vertical ones are not, so gaps
+/// between dashes are filled in when they turn vertical and every other dash is left
+/// out when they turn horizontal.
+pub(crate) fn transform_cells(cells: &[Cell], t: Transform, size: Vec2) -> Vec<Cell> {
+    let chars: HashMap<Vec2, char> = cells.iter().map(|cell| (cell.pos, cell.c)).collect();
+
+    let near = |pos: Vec2| {
+        let chars = &chars;
+        move |dx: isize, dy: isize| {
+            let at = pos.signed() + (dx, dy);
+            if at.x < 0 || at.y < 0 {
+                return None;
+            }
+            chars.get(&at.map(|v| v as usize)).copied()
+        }
+    };
+
+    let mut out = vec![];
+
+    for cell in cells {
+        let near = near(cell.pos);
+        let c = t.glyph_near(cell.c, near);
+
+        // the nth dash of a vertical run, counting from the top
+        let nth = (1..).take_while(|&dy| near(0, -dy) == Some(':')).count();
+        if cell.c == ':' && c == '-' && nth % 2 == 1 {
+            continue;
+        }
+
+        if cell.c == '-' && c == ':' && near(1, 0).is_none() && near(2, 0) == Some('-') {
+            let gap = Cell {
+                pos: cell.pos.map_x(|x| x + 1),
+                c,
+            };
+            out.push(Cell {
+                c,
+                ..gap.transform(t, size)
+            });
+        }
+
+        out.push(Cell {
+            c,
+            ..cell.transform(t, size)
+        });
+    }
+
+    out
+}
+
+/// Returns `cells` as lines of text, with blanks wherever there isn't a cell.
+fn cells_to_text(cells: &[Cell]) -> String {
+    let mut lines: Vec<Vec<char>> = vec![];
+
+    for &Cell { pos, c } in cells {
+        if lines.len() <= pos.y {
+            lines.resize_with(pos.y + 1, Vec::default);
+        }
+        if lines[pos.y].len() <= pos.x {
+            lines[pos.y].resize(pos.x + 1, SP);
+        }
+        lines[pos.y][pos.x] = c;
+    }
+
+    lines
+        .into_iter()
+        .map(|line| line.into_iter().collect::<String>())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Returns a cell for each visible character in `text`.
+fn cells_from_text(text: &str) -> Vec<Cell> {
+    let mut cells = vec![];
+
+    for (y, line) in text.lines().enumerate() {
+        for (x, c) in line.chars().enumerate() {
+            if !c.is_whitespace() {
+                cells.push(Cell {
+                    pos: Vec2::new(x, y),
+                    c,
+                });
+            }
+        }
+    }
+
+    cells
+}
 
 #[derive(Copy, Clone, PartialEq, Eq)]
 pub(crate) enum Char {
@@ -616,16 +1449,125 @@ impl Buffer {
                 .collect::<io::Result<_>>()?,
             edits: vec![],
             cursor: None,
+            journal: Journal::default(),
+            route: None,
         })
     }
 
-    /// Returns a copy of this buffer without any pending edits.
-    fn snapshot(&self) -> Self {
-        Self {
-            chars: self.chars.clone(),
-            edits: vec![],
-            cursor: None,
+    /// Replace all content with that of `other`.
+    fn replace(&mut self, other: Self) {
+        for y in 0..max(self.chars.len(), other.chars.len()) {
+            self.touch(y);
+        }
+        self.chars = other.chars;
+    }
+
+    /// Record the prior state of line `y`, if it hasn't been already.
+    fn touch(&mut self, y: usize) {
+        self.route = None;
+
+        let Journal { len, lines } = &mut self.journal;
+        len.get_or_insert(self.chars.len());
+
+        let chars = &self.chars;
+        lines
+            .entry(y)
+            .or_insert_with(|| chars.get(y).cloned().unwrap_or_default());
+    }
+
+    /// Record the prior state of every line.
+    fn touch_all(&mut self) {
+        for y in 0..self.chars.len() {
+            self.touch(y);
+        }
+    }
+
+    /// Returns a delta of all modifications since the last call to this function.
+    fn take_delta(&mut self) -> Delta {
+        let Journal { len, lines } = mem::take(&mut self.journal);
+
+        let mut delta = Delta {
+            len: (len.unwrap_or(self.chars.len()), self.chars.len()),
+            ..Delta::default()
+        };
+
+        let empty = vec![];
+        for (y, before) in lines {
+            let after = self.chars.get(y).unwrap_or(&empty);
+
+            if before.len() != after.len() {
+                delta.widths.push((y, before.len(), after.len()));
+            }
+
+            for x in 0..max(before.len(), after.len()) {
+                let b = before.get(x).copied().unwrap_or(SP);
+                let a = after.get(x).copied().unwrap_or(SP);
+
+                if b != a {
+                    delta.cells.push((Vec2::new(x, y), b, a));
+                }
+            }
+        }
+
+        delta
+    }
+
+    /// Returns `true` if `delta` was produced from the current buffer state.
+    fn can_apply(&self, delta: &Delta) -> bool {
+        let lines = max(delta.len.0, delta.len.1);
+        let width = |y: usize| self.chars.get(y).map_or(0, Vec::len);
+
+        let mut limits: BTreeMap<usize, usize> = BTreeMap::new();
+        for &(y, before, after) in &delta.widths {
+            if y >= lines || width(y) != before {
+                return false;
+            }
+            limits.insert(y, max(before, after));
+        }
+
+        self.chars.len() == delta.len.0
+            && delta.cells.iter().all(|&(pos, before, _)| {
+                let limit = limits.get(&pos.y).copied().unwrap_or_else(|| width(pos.y));
+                pos.y < lines && pos.x < limit && self.getv(pos).unwrap_or(SP) == before
+            })
+    }
+
+    /// Apply `delta` like `apply`, but record it so that it becomes part of the next
+    /// delta taken.
+    fn replay(&mut self, delta: &Delta) {
+        self.journal.len.get_or_insert(self.chars.len());
+
+        let widths = delta.widths.iter().map(|&(y, _, _)| y);
+        let cells = delta.cells.iter().map(|&(pos, _, _)| pos.y);
+        for y in widths.chain(cells).collect::<BTreeSet<_>>() {
+            self.touch(y);
+        }
+
+        self.apply(delta);
+    }
+
+    /// Apply `delta`, which must have been produced from the current buffer state.
+    fn apply(&mut self, delta: &Delta) {
+        let (_, len) = delta.len;
+        self.route = None;
+
+        if self.chars.len() < max(delta.len.0, len) {
+            self.chars.resize_with(max(delta.len.0, len), Vec::default);
+        }
+
+        for &(y, before, after) in &delta.widths {
+            self.chars[y].resize(max(before, after), SP);
+        }
+
+        for &(Vec2 { x, y }, _, c) in &delta.cells {
+            self.chars[y][x] = c;
         }
+
+        for &(y, _, after) in &delta.widths {
+            self.chars[y].truncate(after);
+        }
+
+        self.chars.truncate(len);
     }
 
     /// Set the cursor position to `pos`.
@@ -643,6 +1585,7 @@ impl Buffer {
         self.chars.clear();
         self.edits.clear();
         self.cursor = None;
+        self.journal = Journal::default();
     }
 
     /// Returns the viewport size required to display all content within the buffer.
@@ -731,6 +1674,8 @@ impl Buffer {
     fn strip_margin_whitespace(&mut self) {
         let is_only_ws = |v: &[char]| v.iter().all(|c| c.is_whitespace());
 
+        self.touch_all();
+
         // upper margin
         for _ in 0..self
             .chars
@@ -767,7 +1712,7 @@ impl Buffer {
                 }
                 let idx = min(line.len() - 1, min_ws);
                 let new = line.split_off(idx);
-                mem::replace(line, new);
+                *line = new;
             }
         }
 
@@ -777,15 +1722,18 @@ impl Buffer {
 
     /// Strip trailing whitespace from the buffer.
     fn strip_trailing_whitespace(&mut self) {
-        for line in self.chars.iter_mut() {
-            let idx = line
+        for y in 0..self.chars.len() {
+            let idx = self.chars[y]
                 .iter()
                 .enumerate()
                 .rfind(|p| !p.1.is_whitespace())
                 .map(|p| p.0 + 1)
                 .unwrap_or(0);
 
-            line.truncate(idx);
+            if idx < self.chars[y].len() {
+                self.touch(y);
+                self.chars[y].truncate(idx);
+            }
         }
     }
 
@@ -814,35 +1762,31 @@ impl Buffer {
         let max_prec = precedence(c);
         let overrides = |_c| _c == c || precedence(_c) > max_prec;
 
-        let mut overridden = false;
-        if self.chars.len() > pos.y && self.chars[pos.y].len() > pos.x {
-            overridden |= overrides(self.chars[pos.y][pos.x]);
-        }
+        let mut pending = self.edits.iter().filter(|cell| cell.pos == pos).peekable();
 
-        overridden |= self
-            .edits
-            .iter()
-            .filter(|cell| cell.pos == pos)
-            .any(|cell| overrides(cell.c));
+        // pending edits replace whatever is in the primary buffer, so only consider the
+        // latter if there aren't any
+        let overridden = if pending.peek().is_some() {
+            pending.any(|cell| overrides(cell.c))
+        } else {
+            self.getv(pos).map(overrides).unwrap_or(false)
+        };
 
         if !overridden {
             self.edits.push(Cell { pos, c });
         }
     }
 
-    /// Set the cell at `(x, y)` to `c`.
-    pub(crate) fn set(&mut self, force: bool, x: usize, y: usize, c: char) {
-        self.setv(force, Vec2::new(x, y), c)
-    }
-
     /// Flush any pending edits to the primary buffer, allocating as necessary.
     fn flush_edits(&mut self) {
         for Cell {
             pos: Vec2 { x, y },
             c,
             ..
-        } in self.edits.drain(..)
+        } in mem::take(&mut self.edits)
         {
+            self.touch(y);
+
             if self.chars.len() <= y {
                 self.chars.resize_with(y + 1, Vec::default);
             }
@@ -858,28 +1802,380 @@ impl Buffer {
         self.edits.clear();
     }
 
+    /// Stamp `c` on every cell along a path through each of `points` in turn, filling in
+    /// any gaps between consecutive points.
+    pub(crate) fn stamp(&mut self, points: &[Vec2], c: char) {
+        if let [pos] = points {
+            self.setv(true, *pos, c);
+        }
+
+        for pair in points.windows(2) {
+            for (x, y) in Bresenham::new(pair[0].signed().pair(), pair[1].signed().pair()) {
+                self.setv(true, Vec2::new(x as usize, y as usize), c);
+            }
+        }
+    }
+
+    /// Fill the region containing `pos` with `c`. The region is bounded by visible
+    /// characters and the extent of the committed content, unless `pos` is itself on a
+    /// visible character, in which case the region is every connected cell holding
+    /// that same character.
+    pub(crate) fn flood_fill(&mut self, pos: Vec2, c: char) {
+        let width = self.chars.iter().map(Vec::len).max().unwrap_or(0);
+        let height = self.chars.len();
+
+        if pos.x >= width || pos.y >= height {
+            return;
+        }
+
+        let target = self.getv(pos).filter(|c| !c.is_whitespace());
+        let fillable = |buf: &Self, pos: Vec2| match target {
+            Some(t) => buf.getv(pos) == Some(t),
+            None => !buf.visible(pos),
+        };
+
+        if target == Some(c) {
+            return;
+        }
+
+        let mut seen = vec![vec![false; width]; height];
+        let mut stack = vec![pos];
+        seen[pos.y][pos.x] = true;
+
+        while let Some(pos) = stack.pop() {
+            self.setv(true, pos, c);
+
+            let Vec2 { x, y } = pos;
+            let next = [
+                (x.checked_sub(1), Some(y)),
+                (Some(x + 1), Some(y)),
+                (Some(x), y.checked_sub(1)),
+                (Some(x), Some(y + 1)),
+            ];
+
+            for (x, y) in next.iter().copied() {
+                let (x, y) = match (x, y) {
+                    (Some(x), Some(y)) if x < width && y < height => (x, y),
+                    _ => continue,
+                };
+
+                if !seen[y][x] && fillable(self, Vec2::new(x, y)) {
+                    seen[y][x] = true;
+                    stack.push(Vec2::new(x, y));
+                }
+            }
+        }
+    }
+
+    /// Draw a box along the edges of `r`.
+    pub(crate) fn draw_box(&mut self, stroke: Stroke, r: Rect) {
+        self.draw_line(stroke, r.top_left(), r.top_right());
+        self.draw_line(stroke, r.top_right(), r.bottom_right());
+        self.draw_line(stroke, r.bottom_right(), r.bottom_left());
+        self.draw_line(stroke, r.bottom_left(), r.top_left());
+
+        // there are no arc glyphs in ascii, so round off the corners by hand
+        if let (Glyphs::Ascii, true) = (stroke.glyphs, stroke.rounded) {
+            self.setv(true, r.top_left(), '.');
+            self.setv(true, r.top_right(), '.');
+            self.setv(true, r.bottom_left(), '\'');
+            self.setv(true, r.bottom_right(), '\'');
+        }
+    }
+
+    /// Draw the largest diamond with 45° sides that fits in the top left of `r`. Each
+    /// side is a diagonal run mirrored from its neighbor, so the vertices are two cells
+    /// wide rather than a crossing of two sides.
+    pub(crate) fn draw_diamond(&mut self, stroke: Stroke, r: Rect) {
+        let n = max(1, min(r.width(), r.height()) / 2);
+        let (x, y) = (r.left(), r.top());
+
+        let rising = stroke.glyphs.diag(XY::new(1, -1));
+        let falling = stroke.glyphs.diag(XY::new(1, 1));
+
+        for i in 0..n {
+            self.setv(false, Vec2::new(x + n - 1 - i, y + i), rising);
+            self.setv(false, Vec2::new(x + n + i, y + i), falling);
+            self.setv(false, Vec2::new(x + i, y + n + i), falling);
+            self.setv(false, Vec2::new(x + 2 * n - 1 - i, y + n + i), rising);
+        }
+    }
+
+    /// Draw a table of `rows` by `cols` cells over `r`, splitting its space as evenly as
+    /// possible. Returns the inside of each cell, in row major order.
+    ///
+    /// Each cell must have room for at least one character.
+    pub(crate) fn draw_table(
+        &mut self,
+        stroke: Stroke,
+        r: Rect,
+        rows: usize,
+        cols: usize,
+    ) -> Vec<Rect> {
+        let split = |lo: usize, hi: usize, n: usize| -> Vec<usize> {
+            (0..=n).map(|i| lo + (hi - lo) * i / n).collect()
+        };
+
+        let ys = split(r.top(), r.bottom(), rows);
+        let xs = split(r.left(), r.right(), cols);
+
+        self.draw_box(stroke, r);
+
+        for &y in &ys[1..rows] {
+            self.draw_line(stroke, Vec2::new(r.left(), y), Vec2::new(r.right(), y));
+        }
+        for &x in &xs[1..cols] {
+            self.draw_line(stroke, Vec2::new(x, r.top()), Vec2::new(x, r.bottom()));
+        }
+
+        // ascii lines don't join where they cross, so put in the junctions by hand
+        if let Glyphs::Ascii = stroke.glyphs {
+            let junction = stroke.glyph(Arms::ALL.with(stroke.weight));
+
+            for &y in &ys[1..rows] {
+                for &x in &xs[1..cols] {
+                    self.setv(false, Vec2::new(x, y), junction);
+                }
+            }
+        }
+
+        ys.windows(2)
+            .flat_map(|y| {
+                xs.windows(2)
+                    .map(move |x| Rect::from_corners((x[0] + 1, y[0] + 1), (x[1] - 1, y[1] - 1)))
+            })
+            .collect()
+    }
+
+    /// Returns the smallest box that has `pos` on one of its edges, along with the
+    /// stroke it was drawn with and the rows of any lines that divide it into
+    /// compartments, like those of a header box.
+    ///
+    /// Boxes stacked directly on top of each other at the same width are compartments of
+    /// a single box.
+    ///
+    /// Does not consider any pending edits.
+    pub(crate) fn find_box(&self, pos: Vec2) -> Option<(Rect, Stroke, Vec<usize>)> {
+        let h = |x: usize, y: usize| self.getv(Vec2::new(x, y)).is_some_and(is_box_h);
+        let v = |x: usize, y: usize| self.getv(Vec2::new(x, y)).is_some_and(is_box_v);
+
+        // returns the inclusive range of cells around `at` that satisfy `f`
+        let run = |at: usize, f: &dyn Fn(usize) -> bool| {
+            let lo = (0..at).rev().take_while(|&i| f(i)).last().unwrap_or(at);
+            let hi = (at + 1..).take_while(|&i| f(i)).last().unwrap_or(at);
+            (lo, hi)
+        };
+
+        let is_box = |r: Rect| {
+            r.width() > 1
+                && r.height() > 1
+                && (r.left()..=r.right()).all(|x| h(x, r.top()) && h(x, r.bottom()))
+                && (r.top()..=r.bottom()).all(|y| v(r.left(), y) && v(r.right(), y))
+        };
+
+        let Vec2 { x, y } = pos;
+        let mut candidates = vec![];
+
+        // pos is on the top or bottom edge
+        if h(x, y) {
+            let (x0, x1) = run(x, &|i| h(i, y));
+
+            for l in (x0..=x).filter(|&l| v(l, y)) {
+                let (y0, y1) = run(y, &|i| v(l, i));
+
+                for r in (x..=x1).filter(|&r| r > l && v(r, y)) {
+                    for o in (y0..=y1).filter(|&o| o != y) {
+                        candidates.push(Rect::from_corners((l, y), (r, o)));
+                    }
+                }
+            }
+        }
+
+        // pos is on the left or right edge
+        if v(x, y) {
+            let (y0, y1) = run(y, &|i| v(x, i));
+
+            for t in (y0..=y).filter(|&t| h(x, t)) {
+                let (x0, x1) = run(x, &|i| h(i, t));
+
+                for b in (y..=y1).filter(|&b| b > t && h(x, b)) {
+                    for o in (x0..=x1).filter(|&o| o != x) {
+                        candidates.push(Rect::from_corners((x, t), (o, b)));
+                    }
+                }
+            }
+        }
+
+        let mut r = candidates
+            .into_iter()
+            .filter(|r| r.contains(pos))
+            .filter(|&r| is_box(r))
+            .min_by_key(|r| r.surface())?;
+
+        // take in any compartments above or below, nearest first
+        let sides = |r: Rect, y: usize| v(r.left(), y) && v(r.right(), y);
+        let mut dividers = vec![];
+
+        while let Some(t) = (0..r.top())
+            .rev()
+            .take_while(|&t| sides(r, t))
+            .find(|&t| is_box(Rect::from_corners((r.left(), t), r.bottom_right())))
+        {
+            dividers.push(r.top());
+            r = Rect::from_corners((r.left(), t), r.bottom_right());
+        }
+
+        while let Some(b) = (r.bottom() + 1..)
+            .take_while(|&b| sides(r, b))
+            .find(|&b| is_box(Rect::from_corners(r.top_left(), (r.right(), b))))
+        {
+            dividers.push(r.bottom());
+            r = Rect::from_corners(r.top_left(), (r.right(), b));
+        }
+
+        dividers.sort_unstable();
+
+        let corner = self.getv(r.top_left())?;
+        let edge = self.getv(r.top_left().map_x(|x| x + 1))?;
+
+        Some((r, Stroke::detect(corner, edge), dividers))
+    }
+
     /// Draw a line from `src` to `dst`.
-    pub(crate) fn draw_line(&mut self, src: Vec2, dst: Vec2) {
+    pub(crate) fn draw_line(&mut self, stroke: Stroke, src: Vec2, dst: Vec2) {
+        self.draw_polyline(stroke, &[src, dst]);
+    }
+
+    /// Draw a line through each of `points` in turn, continuing the stroke's pattern
+    /// across any bends.
+    pub(crate) fn draw_polyline(&mut self, stroke: Stroke, points: &[Vec2]) {
+        let mut points = points.to_vec();
+        points.dedup();
+
+        if let [pos] = points[..] {
+            points.push(pos);
+        }
+
+        let mut phase = 0;
+        for seg in points.windows(2) {
+            phase = self.draw_segment(stroke, seg[0], seg[1], phase);
+        }
+    }
+
+    /// Draw a line from `src` to `dst`, starting at `phase` in the stroke's pattern.
+    /// Returns the phase at `dst`.
+    fn draw_segment(&mut self, stroke: Stroke, src: Vec2, dst: Vec2, phase: usize) -> usize {
+        let Stroke {
+            glyphs,
+            weight,
+            pattern,
+            ..
+        } = stroke;
+
+        let mut last = None;
+        let mut steps = 0;
+
         for (i, (s, e)) in Bresenham::new(src.signed().pair(), dst.signed().pair())
             .steps()
             .enumerate()
         {
-            let c = match line_slope(s, e).pair() {
-                _ if i == 0 => PLUS,
-                (0, _) => PIPE,
-                (_, 0) => DASH,
-                (x, y) if (x > 0) == (y > 0) => GAID,
-                _ => DIAG,
+            let pos = Vec2::new(s.0 as usize, s.1 as usize);
+            let slope = line_slope(s, e);
+            last = Some(slope);
+            steps += 1;
+
+            if i > 0 && pattern.skips(glyphs, slope, phase + i) {
+                continue;
+            }
+
+            if let Glyphs::Unicode = glyphs {
+                match Arms::toward(slope) {
+                    Some(arm) if i == 0 => self.join(stroke, pos, arm, true, PLUS),
+                    Some(arm) => self.join(stroke, pos, arm | arm.opposite(), false, PLUS),
+                    None if i == 0 => self.join(stroke, pos, Arms::NONE, true, glyphs.diag(slope)),
+                    None => self.setv(false, pos, glyphs.diag(slope)),
+                }
+                continue;
+            }
+
+            let c = match slope.pair() {
+                _ if i == 0 => stroke.glyph(Arms::ALL.with(weight)),
+                (0, _) => stroke.glyph((N | S).with(weight)),
+                (_, 0) => stroke.glyph((E | W).with(weight)),
+                _ => glyphs.diag(slope),
             };
 
-            self.set(false, s.0 as usize, s.1 as usize, c);
+            self.setv(false, pos, c);
+        }
+
+        match (glyphs, last) {
+            (Glyphs::Ascii, _) => self.setv(false, dst, stroke.glyph(Arms::ALL.with(weight))),
+
+            (_, Some(slope)) => match Arms::toward(slope) {
+                Some(arm) => self.join(stroke, dst, arm.opposite(), true, PLUS),
+                None => self.join(stroke, dst, Arms::NONE, true, glyphs.diag(slope)),
+            },
+
+            (_, None) => self.join(stroke, dst, Arms::NONE, true, stroke.glyph(Arms::ALL)),
+        }
+
+        phase + steps
+    }
+
+    /// Returns the character at `pos`, considering any pending edits.
+    fn peek(&self, pos: Vec2) -> Option<char> {
+        self.edits
+            .iter()
+            .rev()
+            .find(|cell| cell.pos == pos)
+            .map(|cell| cell.c)
+            .or_else(|| self.getv(pos))
+    }
+
+    /// Returns the directions in which the neighbors of `pos` have an arm pointing back
+    /// towards `pos`, weighted like the arm that points back.
+    fn connecting(&self, pos: Vec2) -> Arms {
+        Arms::DIRS
+            .iter()
+            .copied()
+            .filter_map(|dir| {
+                step(pos, dir)
+                    .and_then(|next| self.peek(next))
+                    .and_then(Arms::of)
+                    .map(|arms| (arms & dir.opposite()).opposite())
+            })
+            .fold(Arms::NONE, |acc, arm| acc | arm)
+    }
+
+    /// Set the cell at `pos` to the line glyph with `own` arms (in the weight of
+    /// `stroke`), merged with those of any existing line glyph there that are still
+    /// connected to something. Endpoints also connect to any neighboring line glyph that
+    /// points towards `pos`.
+    ///
+    /// If the result has no arms at all, `bare` is drawn instead.
+    fn join(&mut self, stroke: Stroke, pos: Vec2, own: Arms, endpoint: bool, bare: char) {
+        let conn = self.connecting(pos);
+        let existing = self.peek(pos).and_then(Arms::of).unwrap_or_default();
+
+        let mut arms = own.with(stroke.weight) | (existing & conn);
+        if endpoint {
+            arms = arms | conn;
         }
 
-        self.setv(false, dst, PLUS);
+        let bare = match self.peek(pos) {
+            Some(c) if c != bare && "╱╲".contains(c) && "╱╲".contains(bare) => '╳',
+            _ => bare,
+        };
+
+        if arms == Arms::NONE {
+            self.setv(false, pos, bare);
+        } else {
+            self.setv(true, pos, stroke.glyph(arms));
+        }
     }
 
-    /// Draw an arrow tip for an arrow from `src` to `dst`.
-    pub(crate) fn draw_arrow_tip(&mut self, src: Vec2, dst: Vec2) {
+    /// Draw an arrowhead for an arrow from `src` to `dst`.
+    pub(crate) fn draw_arrow_tip(&mut self, glyphs: Glyphs, head: Arrowhead, src: Vec2, dst: Vec2) {
         let dec = |v: usize| v - 1;
         let inc = |v: usize| v + 1;
 
@@ -927,10 +2223,70 @@ impl Buffer {
             (x, y) if x < 0 && y < 0 && self.visible(dst.map_x(dec)) => W,
             (x, y) if x < 0 && y < 0 => N,
 
-            (_, _) => PLUS,
+            (_, _) => Arms::NONE,
+        };
+
+        // heads that are more than one glyph long only fit if they point along the line
+        let back = match (tip, line_slope(src, dst).pair()) {
+            (N, S_N) => Some(dst.map_y(|y| y + 1)),
+            (E, S_E) => dst.checked_sub((1, 0)),
+            (S, S_S) => dst.checked_sub((0, 1)),
+            (W, S_W) => Some(dst.map_x(|x| x + 1)),
+            _ => None,
+        };
+
+        match (head.glyphs(glyphs, tip).as_slice(), back) {
+            (&[c, b], Some(back)) => {
+                self.setv(true, dst, c);
+                self.setv(true, back, b);
+            }
+            (&[c, ..], _) => self.setv(true, dst, c),
+            _ => {}
+        }
+    }
+
+    /// Write `label` next to the line through each of `points`. It is placed at the
+    /// midpoint of the longest straight segment, above horizontal segments and to the
+    /// right of anything else, so that none of the line itself is overwritten.
+    pub(crate) fn draw_label(&mut self, points: &[Vec2], label: &str) {
+        let mut segments: Vec<(Vec2, Vec2)> = vec![];
+
+        for pair in points.windows(2) {
+            let (src, dst) = (pair[0], pair[1]);
+            if src == dst {
+                continue;
+            }
+
+            match segments.last_mut() {
+                Some((s, d)) if *d == src && line_slope(*s, *d) == line_slope(src, dst) => *d = dst,
+                _ => segments.push((src, dst)),
+            }
+        }
+
+        let len = |&(s, d): &(Vec2, Vec2)| max(diff(s.x, d.x), diff(s.y, d.y));
+
+        // reversed, as max_by_key returns the last of several equally long segments
+        let (src, dst) = match segments.iter().rev().max_by_key(|seg| len(seg)) {
+            Some(&seg) => seg,
+            None => return,
+        };
+
+        let mid = (src + dst) / 2;
+        let width = label.chars().count();
+
+        let pos = if src.y == dst.y {
+            let x = mid.x.saturating_sub(width / 2);
+            match mid.y {
+                0 => Vec2::new(x, 1),
+                y => Vec2::new(x, y - 1),
+            }
+        } else {
+            mid.map_x(|x| x + 2)
         };
 
-        self.setv(true, dst, tip);
+        for (i, c) in label.chars().enumerate() {
+            self.setv(true, pos.map_x(|x| x + i), c);
+        }
     }
 
     pub(crate) fn snap45(&self, src: Vec2, dst: Vec2) -> Vec2 {
@@ -951,71 +2307,161 @@ impl Buffer {
     }
 
     pub(crate) fn snap90(&self, src: Vec2, dst: Vec2) -> Vec2 {
-        if let Some(DASH) = self.getv(dst) {
+        if let Some(true) = (self.getv(dst).and_then(Arms::of)).map(|a| a.with(Light) == E | W) {
             Vec2::new(dst.x, src.y)
         } else {
             Vec2::new(src.x, dst.y)
         }
     }
 
-    /// Draw the shortest path from `src` to `dst`. Returns the penultimate point
-    /// along that path.
-    pub(crate) fn draw_path(&mut self, src: Vec2, dst: Vec2) -> Vec2 {
-        let mut path = astar(
-            &src.pair(),
-            |&pos| self.neighbors(pos),
-            |&pos| heuristic(pos.into(), dst),
-            |&pos| pos == dst.pair(),
-        )
-        .map(|(points, _)| points)
-        .unwrap()
-        .into_iter()
-        .map(Vec2::from)
-        .enumerate()
-        .peekable();
+    /// Draw the shortest path from `src` to `dst`, using only cardinal moves if `mode`
+    /// is `PathMode::RoutedOrthogonal`. Returns every point along that path.
+    pub(crate) fn draw_path(
+        &mut self,
+        stroke: Stroke,
+        mode: PathMode,
+        src: Vec2,
+        dst: Vec2,
+    ) -> Vec<Vec2> {
+        let Stroke {
+            glyphs, pattern, ..
+        } = stroke;
+
+        let orthogonal = matches!(mode, PathMode::RoutedOrthogonal);
+        let path = self.route(src, dst, orthogonal);
 
         let decide = |i: usize, last: Vec2, pos: Vec2| -> char {
             match line_slope(last, pos).pair() {
                 _ if i == 0 => PLUS,
                 (0, _) => PIPE,
                 (_, 0) => DASH,
-                (x, y) if (x > 0) == (y > 0) => GAID,
-                _ => DIAG,
+                _ => glyphs.diag(line_slope(last, pos)),
             }
         };
 
-        let mut last = src;
-        while let Some((i, pos)) = path.next() {
+        for (i, &pos) in path.iter().enumerate() {
+            let last = path[i.saturating_sub(1)];
+            let next = path.get(i + 1).copied();
+
+            if let Glyphs::Unicode = glyphs {
+                let slopes = [
+                    line_slope(pos, last),
+                    next.map_or(XY::new(0, 0), |n| line_slope(pos, n)),
+                ];
+
+                let own = (slopes.iter().copied())
+                    .filter_map(Arms::toward)
+                    .fold(Arms::NONE, |acc, arm| acc | arm);
+
+                let mut diags = (slopes.iter().copied())
+                    .filter(|slope| *slope != XY::new(0, 0) && Arms::toward(*slope).is_none())
+                    .map(|slope| glyphs.diag(slope));
+
+                let bare = match (diags.next(), diags.next()) {
+                    (Some(a), Some(b)) if a != b => glyphs.cross(),
+                    (Some(a), _) => a,
+                    _ => glyphs.line(Arms::ALL, false),
+                };
+
+                let through = slopes[0] + slopes[1] == XY::new(0, 0);
+                if through && pattern.skips(glyphs, slopes[1], i) {
+                    continue;
+                }
+
+                self.join(stroke, pos, own, i == 0 || next.is_none(), bare);
+                continue;
+            }
+
             let mut c = decide(i, last, pos);
 
-            if let Some(next) = path.peek().map(|(i, next)| decide(*i, pos, *next)) {
+            if let Some(next) = next.map(|next| decide(i + 1, pos, next)) {
                 if c != PLUS && next != c {
                     c = PLUS;
                 }
-                last = pos;
             }
 
+            if c != PLUS && pattern.skips(glyphs, line_slope(last, pos), i) {
+                continue;
+            }
+
+            let c = match c {
+                PIPE => stroke.glyph(N | S),
+                DASH => stroke.glyph(E | W),
+                c => c,
+            };
+
             self.setv(false, pos, c);
         }
-        self.setv(false, dst, PLUS);
 
-        last
+        if let Glyphs::Ascii = glyphs {
+            self.setv(false, dst, PLUS);
+        }
+
+        path
     }
 
-    /// Returns the coordinates neighboring `pos`, along with the cost to reach each one.
-    fn neighbors(&self, pos: (usize, usize)) -> Vec<((usize, usize), OrdFloat)> {
+    /// Returns the shortest path from `src` to `dst`, reusing the last one found if
+    /// neither the endpoints nor the committed content have changed since.
+    ///
+    /// The search is confined to the bounding box of `src` and `dst`, plus a margin of
+    /// `ROUTE_MARGIN` cells on each side to allow for detours.
+    fn route(&mut self, src: Vec2, dst: Vec2, orthogonal: bool) -> Vec<Vec2> {
+        if let Some(route) = &self.route {
+            if route.src == src && route.dst == dst && route.orthogonal == orthogonal {
+                return route.path.clone();
+            }
+        }
+
+        let r = Rect::from_corners(src, dst);
+        let bounds = Rect::from_corners(
+            r.top_left().saturating_sub((ROUTE_MARGIN, ROUTE_MARGIN)),
+            r.bottom_right() + (ROUTE_MARGIN, ROUTE_MARGIN),
+        );
+
+        // every cell is passable, so a path within bounds always exists
+        let path: Vec<Vec2> = if orthogonal {
+            astar(
+                &(src.pair(), None),
+                |&(pos, dir)| self.cardinal_neighbors(pos, dir, bounds),
+                |&(pos, _)| manhattan(pos.into(), dst),
+                |&(pos, _)| pos == dst.pair(),
+            )
+            .map(|(states, _)| states.into_iter().map(|(pos, _)| pos).collect())
+        } else {
+            astar(
+                &src.pair(),
+                |&pos| self.neighbors(pos, bounds),
+                |&pos| heuristic(pos.into(), dst),
+                |&pos| pos == dst.pair(),
+            )
+            .map(|(points, _)| points)
+        }
+        .unwrap()
+        .into_iter()
+        .map(Vec2::from)
+        .collect();
+
+        self.route = Some(Route {
+            src,
+            dst,
+            orthogonal,
+            path: path.clone(),
+        });
+
+        path
+    }
+
+    /// Returns the coordinates neighboring `pos` within `bounds`, along with the cost to
+    /// reach each one.
+    fn neighbors(&self, pos: (usize, usize), bounds: Rect) -> Vec<((usize, usize), usize)> {
         let vis = |pos: (usize, usize)| self.visible(pos.into());
 
-        let card = |pos| (pos, OrdFloat((vis(pos) as u8 as f64 * 64.0) + D));
+        let card = |pos| (pos, vis(pos) as usize * SPOT + D);
 
         let diag = |pos, (c1, c2)| {
-            let cost = {
-                let spot = vis(pos) as u8 as f64 * 64.0;
-                let edge = (vis(c1) && vis(c2)) as u8 as f64 * 64.0;
-                spot + edge
-            };
+            let cost = vis(pos) as usize * SPOT + (vis(c1) && vis(c2)) as usize * SPOT;
 
-            (pos, OrdFloat(cost + D2))
+            (pos, cost + D2)
         };
 
         let w = |(x, y)| (x - 1, y);
@@ -1039,22 +2485,63 @@ impl Buffer {
         succ.push(card(s(pos)));
         succ.push(diag(s(e(pos)), (s(pos), e(pos))));
 
+        succ.retain(|&(pos, _)| bounds.contains(pos.into()));
         succ
     }
+
+    /// Returns the cardinal neighbors of `pos` within `bounds` when arriving from
+    /// direction `dir`, along with the cost to reach each one. Changing direction
+    /// costs an extra `TURN`.
+    #[allow(clippy::type_complexity)]
+    fn cardinal_neighbors(
+        &self,
+        pos: (usize, usize),
+        dir: Option<(isize, isize)>,
+        bounds: Rect,
+    ) -> Vec<(((usize, usize), Option<(isize, isize)>), usize)> {
+        [S_N, S_E, S_S, S_W]
+            .iter()
+            .filter(|&&step| dir != Some((-step.0, -step.1)))
+            .filter_map(|&step| {
+                let next = (
+                    pos.0.checked_add_signed(step.0)?,
+                    pos.1.checked_add_signed(step.1)?,
+                );
+
+                if !bounds.contains(next.into()) {
+                    return None;
+                }
+
+                let turn = dir.is_some_and(|d| d != step) as usize * TURN;
+                let cost = self.visible(next.into()) as usize * SPOT + D + turn;
+
+                Some(((next, Some(step)), cost))
+            })
+            .collect()
+    }
 }
 
+/// How far a routed path may stray outside the bounding box of its endpoints.
+const ROUTE_MARGIN: usize = 16;
+
 /// Cost to move one step on the cardinal plane.
-const D: f64 = 1.0;
+const D: usize = 1000;
+
+/// Cost to move one step on the diagonal plane, approximating `D * sqrt(2)`.
+const D2: usize = 1414;
 
-/// Cost to move one step on the diagonal plane.
-const D2: f64 = SQRT_2;
+/// Cost to move through (or diagonally between) visible characters.
+const SPOT: usize = 64 * D;
+
+/// Cost to change direction along an orthogonal route.
+const TURN: usize = 4 * D;
 
 /// Returns a distance heuristic between `pos` and `dst`.
-fn heuristic(pos: Vec2, dst: Vec2) -> OrdFloat {
+fn heuristic(pos: Vec2, dst: Vec2) -> usize {
     // base is diagonal distance:
     // http://theory.stanford.edu/~amitp/GameProgramming/Heuristics.html#diagonal-distance
-    let dx = (pos.x as f64 - dst.x as f64).abs();
-    let dy = (pos.y as f64 - dst.y as f64).abs();
+    let dx = max(pos.x, dst.x) - min(pos.x, dst.x);
+    let dy = max(pos.y, dst.y) - min(pos.y, dst.y);
 
     let dist = if dx > dy {
         D * (dx - dy) + D2 * dy
@@ -1064,42 +2551,20 @@ fn heuristic(pos: Vec2, dst: Vec2) -> OrdFloat {
 
     // prefer to expand paths close to dst:
     // http://theory.stanford.edu/~amitp/GameProgramming/Heuristics.html#breaking-ties
-    const P: f64 = 1.0 + (1.0 / 1000.0);
-
-    OrdFloat(dist * P)
+    dist + dist / 1000
 }
 
-#[derive(PartialEq, PartialOrd, Copy, Clone)]
-struct OrdFloat(f64);
-
-impl Eq for OrdFloat {}
-
-impl Ord for OrdFloat {
-    #[inline]
-    fn cmp(&self, other: &Self) -> std::cmp::Ordering {
-        self.partial_cmp(other).unwrap()
-    }
-}
+/// Returns a distance heuristic between `pos` and `dst` for orthogonal routes.
+fn manhattan(pos: Vec2, dst: Vec2) -> usize {
+    let dx = max(pos.x, dst.x) - min(pos.x, dst.x);
+    let dy = max(pos.y, dst.y) - min(pos.y, dst.y);
 
-impl Add<Self> for OrdFloat {
-    type Output = Self;
+    // at least one turn is needed unless `dst` is in line with `pos`
+    let turn = (dx > 0 && dy > 0) as usize * TURN;
+    let dist = D * (dx + dy) + turn;
 
-    #[inline]
-    fn add(self, other: Self) -> Self {
-        Self(self.0 + other.0)
-    }
-}
-
-impl Zero for OrdFloat {
-    #[inline]
-    fn zero() -> Self {
-        Self(0.0)
-    }
-
-    #[inline]
-    fn is_zero(&self) -> bool {
-        self.0 == 0.0
-    }
+    // prefer to expand paths close to dst, as in `heuristic`
+    dist + dist / 1000
 }
 
 /// Returns the overlap precedence for `c`.
@@ -1110,10 +2575,38 @@ fn precedence(c: char) -> usize {
         PIPE => 3,
         DIAG => 2,
         GAID => 1,
+        '#' => 5,
+        '=' => 4,
+        c if Glyphs::Unicode.is_line(c) => 4,
+        '╳' => 3,
+        '╱' => 2,
+        '╲' => 1,
         _ => 0,
     }
 }
 
+/// Returns `true` if `c` could be part of the top or bottom edge of a box.
+fn is_box_h(c: char) -> bool {
+    "+-=#.'".contains(c) || Arms::of(c).is_some_and(|a| a & (E | W) != Arms::NONE)
+}
+
+/// Returns `true` if `c` could be part of the left or right edge of a box.
+fn is_box_v(c: char) -> bool {
+    "+|#.'".contains(c) || Arms::of(c).is_some_and(|a| a & (N | S) != Arms::NONE)
+}
+
+/// Returns the position one step from `pos` in the direction of `dir`, if it is on the
+/// canvas.
+fn step(pos: Vec2, dir: Arms) -> Option<Vec2> {
+    match dir {
+        Arms::N if pos.y > 0 => Some(pos.map_y(|y| y - 1)),
+        Arms::E => Some(pos.map_x(|x| x + 1)),
+        Arms::S => Some(pos.map_y(|y| y + 1)),
+        Arms::W if pos.x > 0 => Some(pos.map_x(|x| x - 1)),
+        _ => None,
+    }
+}
+
 /// Returns `true` if `a` is within `w` of `b` (inclusive).
 fn within(w: usize, a: usize, b: usize) -> bool {
     diff(a, b) <= w
@@ -1121,7 +2614,7 @@ fn within(w: usize, a: usize, b: usize) -> bool {
 
 /// Returns the absolute difference between `a` and `b`.
 fn diff(a: usize, b: usize) -> usize {
-    (a as isize - b as isize).abs() as usize
+    (a as isize - b as isize).unsigned_abs()
 }
 
 /// Returns the slope between points at `src` and `dst`.
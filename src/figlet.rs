@@ -0,0 +1,179 @@
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use lazy_static::lazy_static;
+use std::{
+    collections::HashMap,
+    fmt,
+    fs::File,
+    io::{self, BufRead, BufReader, ErrorKind},
+    path::Path,
+    sync::Arc,
+};
+
+/// Characters that every FIGlet font defines in order, after the printable ASCII range.
+const DEUTSCH: [char; 7] = ['Ä', 'Ö', 'Ü', 'ä', 'ö', 'ü', 'ß'];
+
+lazy_static! {
+    static ref BLOCK: Arc<Font> = Arc::new(
+        Font::read_from(&include_bytes!("../fonts/block.flf")[..]).expect("built-in font is valid")
+    );
+}
+
+/// A FIGlet font.
+pub(crate) struct Font {
+    height: usize,
+    glyphs: HashMap<char, Vec<Vec<char>>>,
+}
+
+impl fmt::Debug for Font {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Font(height: {}, glyphs: {})",
+            self.height,
+            self.glyphs.len()
+        )
+    }
+}
+
+fn invalid<T>(msg: &str) -> io::Result<T> {
+    Err(io::Error::new(ErrorKind::InvalidData, msg))
+}
+
+impl Font {
+    /// Returns the built-in block font.
+    pub(crate) fn block() -> Arc<Self> {
+        Arc::clone(&BLOCK)
+    }
+
+    /// Load a font from the `.flf` file at `path`.
+    pub(crate) fn open<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Self::read_from(File::open(path)?)
+    }
+
+    fn read_from<R: io::Read>(r: R) -> io::Result<Self> {
+        let mut lines = BufReader::new(r).split(b'\n').map(|lr| {
+            lr.map(|line| {
+                let line = String::from_utf8_lossy(&line);
+                line.trim_end_matches(&['\r', '\n'][..]).to_owned()
+            })
+        });
+
+        let header = match lines.next() {
+            Some(line) => line?,
+            None => return invalid("empty font file"),
+        };
+
+        if !header.starts_with("flf2a") {
+            return invalid("missing flf2a signature");
+        }
+
+        let hardblank = match header.chars().nth(5) {
+            Some(c) => c,
+            None => return invalid("missing hardblank"),
+        };
+
+        let params: Vec<i64> = header[5 + hardblank.len_utf8()..]
+            .split_whitespace()
+            .map(|p| p.parse().or_else(|_| invalid("malformed header")))
+            .collect::<io::Result<_>>()?;
+
+        let (height, comments) = match (params.first(), params.get(4)) {
+            (Some(&h), Some(&c)) if h > 0 && c >= 0 => (h as usize, c as usize),
+            _ => return invalid("malformed header"),
+        };
+
+        for line in lines.by_ref().take(comments) {
+            line?;
+        }
+
+        // reads the next glyph, or none if the file ended before it started
+        let next_glyph = |lines: &mut dyn Iterator<Item = io::Result<String>>| {
+            let mut rows = Vec::with_capacity(height);
+
+            for line in lines.take(height) {
+                let line = line?;
+                let end = line.trim_end().chars().last();
+                let row = line.trim_end().trim_end_matches(|c| Some(c) == end);
+                rows.push(
+                    row.chars()
+                        .map(|c| if c == hardblank { ' ' } else { c })
+                        .collect(),
+                );
+            }
+
+            match rows.len() {
+                0 => Ok(None),
+                n if n == height => Ok(Some(rows)),
+                _ => invalid("truncated character"),
+            }
+        };
+
+        let mut glyphs = HashMap::new();
+
+        let required = (' '..='~').chain(DEUTSCH.iter().copied());
+        for c in required {
+            match next_glyph(&mut lines)? {
+                Some(rows) => glyphs.insert(c, rows),
+                None if c > '~' => break,
+                None => return invalid("missing required characters"),
+            };
+        }
+
+        // code tagged characters
+        while let Some(tag) = lines.next() {
+            let tag = tag?;
+            let code = match tag.split_whitespace().next() {
+                Some(code) => code,
+                None => continue,
+            };
+
+            let rows = match next_glyph(&mut lines)? {
+                Some(rows) => rows,
+                None => return invalid("truncated character"),
+            };
+
+            if let Some(c) = parse_code(code).and_then(std::char::from_u32) {
+                glyphs.insert(c, rows);
+            }
+        }
+
+        Ok(Self { height, glyphs })
+    }
+
+    /// Returns the height of each line of text rendered in this font.
+    pub(crate) fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the rows of `line` rendered in this font. Characters that the font does
+    /// not define are skipped.
+    pub(crate) fn render(&self, line: &[char]) -> Vec<Vec<char>> {
+        let mut rows = vec![vec![]; self.height];
+
+        for glyph in line.iter().filter_map(|c| self.glyphs.get(c)) {
+            let width = glyph.iter().map(Vec::len).max().unwrap_or(0);
+
+            for (row, part) in rows.iter_mut().zip(glyph) {
+                row.extend(part);
+                row.resize(row.len() + width - part.len(), ' ');
+            }
+        }
+
+        rows
+    }
+}
+
+/// Parses a character code in decimal, octal (leading `0`), or hexadecimal (leading
+/// `0x`). Negative codes are not mapped to any character, so they yield `None`.
+fn parse_code(code: &str) -> Option<u32> {
+    if let Some(hex) = code.strip_prefix("0x").or_else(|| code.strip_prefix("0X")) {
+        u32::from_str_radix(hex, 16).ok()
+    } else if code.len() > 1 && code.starts_with('0') {
+        u32::from_str_radix(&code[1..], 8).ok()
+    } else {
+        code.parse().ok()
+    }
+}
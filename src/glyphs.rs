@@ -0,0 +1,546 @@
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+use cursive::XY;
+use std::{
+    fmt,
+    ops::{BitAnd, BitOr},
+    str::FromStr,
+};
+
+/// The set of characters used by the drawing primitives.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Glyphs {
+    #[default]
+    Ascii,
+    Unicode,
+}
+
+impl fmt::Display for Glyphs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ascii => write!(f, "ascii"),
+            Self::Unicode => write!(f, "unicode"),
+        }
+    }
+}
+
+impl FromStr for Glyphs {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ascii" => Ok(Self::Ascii),
+            "unicode" => Ok(Self::Unicode),
+            _ => Err(format!(
+                "unknown glyph set {:?} (expected ascii or unicode)",
+                s
+            )),
+        }
+    }
+}
+
+impl Glyphs {
+    /// Returns the glyph for a diagonal line with the provided `slope`.
+    pub(crate) fn diag(self, slope: XY<isize>) -> char {
+        let falling = (slope.x > 0) == (slope.y > 0);
+
+        match self {
+            Self::Ascii if falling => '\\',
+            Self::Ascii => '/',
+            Self::Unicode if falling => '╲',
+            Self::Unicode => '╱',
+        }
+    }
+
+    /// Returns the glyph used where two diagonal lines cross.
+    pub(crate) fn cross(self) -> char {
+        match self {
+            Self::Ascii => '+',
+            Self::Unicode => '╳',
+        }
+    }
+
+    /// Returns the glyph for an arrow tip pointing in the direction of `arms`.
+    pub(crate) fn tip(self, arms: Arms) -> char {
+        match (self, arms) {
+            (Self::Ascii, Arms::N) => '^',
+            (Self::Ascii, Arms::E) => '>',
+            (Self::Ascii, Arms::S) => 'v',
+            (Self::Ascii, Arms::W) => '<',
+            (Self::Unicode, Arms::N) => '▲',
+            (Self::Unicode, Arms::E) => '▶',
+            (Self::Unicode, Arms::S) => '▼',
+            (Self::Unicode, Arms::W) => '◀',
+            _ => self.line(Arms::ALL, false),
+        }
+    }
+
+    /// Returns the glyph that connects to its neighbors in the directions of `arms`,
+    /// using arcs for light corners if `rounded` is set.
+    ///
+    /// Lone arms are extended into a straight line, as half lines don't render well in
+    /// most fonts. If there is no glyph for the exact combination of weights in `arms`,
+    /// the closest available one is used.
+    pub(crate) fn line(self, arms: Arms, rounded: bool) -> char {
+        let arms = match arms.with(Weight::Light) {
+            Arms::N | Arms::S => arms | arms.opposite(),
+            Arms::E | Arms::W => arms | arms.opposite(),
+            Arms::NONE => Arms::ALL,
+            _ => arms,
+        };
+
+        if let Self::Ascii = self {
+            return match (arms.heaviest(), arms.with(Weight::Light)) {
+                (Weight::Heavy, _) => '#',
+                (Weight::Double, a) if a == Arms::E | Arms::W => '=',
+                (_, a) if a == Arms::E | Arms::W => '-',
+                (_, a) if a == Arms::N | Arms::S => '|',
+                _ => '+',
+            };
+        }
+
+        if rounded {
+            if let Some(c) = find(ARCS, arms) {
+                return c;
+            }
+        }
+
+        find(BOX, arms)
+            .or_else(|| find(BOX, arms.balanced()))
+            .or_else(|| find(BOX, arms.with(arms.heaviest())))
+            .unwrap()
+    }
+
+    /// Returns `true` if `c` is a line glyph of this set.
+    pub(crate) fn is_line(self, c: char) -> bool {
+        match self {
+            Self::Ascii => "-|+=#".contains(c),
+            Self::Unicode => BOX.iter().chain(ARCS).chain(DASHES).any(|(l, _)| *l == c),
+        }
+    }
+}
+
+/// Returns the glyph in `table` that has exactly `arms`.
+fn find(table: &[(char, Arms)], arms: Arms) -> Option<char> {
+    table.iter().find(|(_, a)| *a == arms).map(|(c, _)| *c)
+}
+
+/// The weight of a single arm of a line glyph.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub(crate) enum Weight {
+    #[default]
+    Light = 1,
+    Heavy = 2,
+    Double = 3,
+}
+
+/// The directions (and weights) in which a line glyph connects to its neighbors.
+///
+/// Each direction is packed into 2 bits, which hold the weight of that arm or zero if
+/// it isn't present.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Arms(u8);
+
+impl Arms {
+    pub(crate) const NONE: Self = Self(0);
+    pub(crate) const N: Self = Self(1);
+    pub(crate) const E: Self = Self(1 << 2);
+    pub(crate) const S: Self = Self(1 << 4);
+    pub(crate) const W: Self = Self(1 << 6);
+    pub(crate) const ALL: Self = Self(0b0101_0101);
+
+    /// Each cardinal direction, in clockwise order.
+    pub(crate) const DIRS: [Self; 4] = [Self::N, Self::E, Self::S, Self::W];
+
+    const fn new(n: u8, e: u8, s: u8, w: u8) -> Self {
+        Self(n | e << 2 | s << 4 | w << 6)
+    }
+
+    /// Returns the arms of the line glyph `c`, if it is one.
+    pub(crate) fn of(c: char) -> Option<Self> {
+        match c {
+            '-' => Some(Self::E | Self::W),
+            '|' => Some(Self::N | Self::S),
+            c => BOX
+                .iter()
+                .chain(ARCS)
+                .chain(DASHES)
+                .find(|(l, _)| *l == c)
+                .map(|(_, a)| *a),
+        }
+    }
+
+    /// Returns the direction of a cardinal `slope`, or `None` if it is diagonal (or
+    /// zero).
+    pub(crate) fn toward(slope: XY<isize>) -> Option<Self> {
+        match slope.pair() {
+            (0, y) if y < 0 => Some(Self::N),
+            (x, 0) if x > 0 => Some(Self::E),
+            (0, y) if y > 0 => Some(Self::S),
+            (x, 0) if x < 0 => Some(Self::W),
+            _ => None,
+        }
+    }
+
+    /// Returns one bit per present arm, in the lowest bit of its slot.
+    fn present(self) -> u8 {
+        (self.0 | self.0 >> 1) & Self::ALL.0
+    }
+
+    /// Returns a mask covering every slot with a present arm.
+    fn mask(self) -> u8 {
+        self.present() * 0b11
+    }
+
+    /// Returns the same arms, all of weight `w`.
+    pub(crate) fn with(self, w: Weight) -> Self {
+        Self(self.present() * w as u8)
+    }
+
+    /// Returns the heaviest weight among the arms.
+    pub(crate) fn heaviest(self) -> Weight {
+        match (0..4).map(|i| self.0 >> (i * 2) & 0b11).max() {
+            Some(3) => Weight::Double,
+            Some(2) => Weight::Heavy,
+            _ => Weight::Light,
+        }
+    }
+
+    /// Returns the arms with opposing arms of differing weight set to the heavier of the
+    /// two.
+    fn balanced(self) -> Self {
+        let axis = |a: Self| a.with(a.heaviest());
+
+        axis(self & (Self::N | Self::S)) | axis(self & (Self::E | Self::W))
+    }
+
+    /// Returns the arms pointing in the opposite directions.
+    pub(crate) fn opposite(self) -> Self {
+        Self(self.0.rotate_left(4))
+    }
+}
+
+/// Returns the union of two sets of arms. Where both have an arm in the same direction,
+/// the weight from the left hand side is kept.
+impl BitOr for Arms {
+    type Output = Self;
+
+    fn bitor(self, other: Self) -> Self {
+        Self(self.0 | (other.0 & !self.mask()))
+    }
+}
+
+/// Returns the arms on the left hand side that point in a direction present on the
+/// right hand side.
+impl BitAnd for Arms {
+    type Output = Self;
+
+    fn bitand(self, other: Self) -> Self {
+        Self(self.0 & other.mask())
+    }
+}
+
+/// Box drawing glyphs, including mixed weight junctions.
+#[rustfmt::skip]
+const BOX: &[(char, Arms)] = &[
+    ('─', Arms::new(0, 1, 0, 1)), ('━', Arms::new(0, 2, 0, 2)), ('═', Arms::new(0, 3, 0, 3)),
+    ('│', Arms::new(1, 0, 1, 0)), ('┃', Arms::new(2, 0, 2, 0)), ('║', Arms::new(3, 0, 3, 0)),
+    ('╼', Arms::new(0, 2, 0, 1)), ('╾', Arms::new(0, 1, 0, 2)),
+    ('╽', Arms::new(1, 0, 2, 0)), ('╿', Arms::new(2, 0, 1, 0)),
+
+    ('┌', Arms::new(0, 1, 1, 0)), ('┍', Arms::new(0, 2, 1, 0)), ('┎', Arms::new(0, 1, 2, 0)),
+    ('┏', Arms::new(0, 2, 2, 0)), ('╒', Arms::new(0, 3, 1, 0)), ('╓', Arms::new(0, 1, 3, 0)),
+    ('╔', Arms::new(0, 3, 3, 0)),
+
+    ('┐', Arms::new(0, 0, 1, 1)), ('┑', Arms::new(0, 0, 1, 2)), ('┒', Arms::new(0, 0, 2, 1)),
+    ('┓', Arms::new(0, 0, 2, 2)), ('╕', Arms::new(0, 0, 1, 3)), ('╖', Arms::new(0, 0, 3, 1)),
+    ('╗', Arms::new(0, 0, 3, 3)),
+
+    ('└', Arms::new(1, 1, 0, 0)), ('┕', Arms::new(1, 2, 0, 0)), ('┖', Arms::new(2, 1, 0, 0)),
+    ('┗', Arms::new(2, 2, 0, 0)), ('╘', Arms::new(1, 3, 0, 0)), ('╙', Arms::new(3, 1, 0, 0)),
+    ('╚', Arms::new(3, 3, 0, 0)),
+
+    ('┘', Arms::new(1, 0, 0, 1)), ('┙', Arms::new(1, 0, 0, 2)), ('┚', Arms::new(2, 0, 0, 1)),
+    ('┛', Arms::new(2, 0, 0, 2)), ('╛', Arms::new(1, 0, 0, 3)), ('╜', Arms::new(3, 0, 0, 1)),
+    ('╝', Arms::new(3, 0, 0, 3)),
+
+    ('├', Arms::new(1, 1, 1, 0)), ('┝', Arms::new(1, 2, 1, 0)), ('┞', Arms::new(2, 1, 1, 0)),
+    ('┟', Arms::new(1, 1, 2, 0)), ('┠', Arms::new(2, 1, 2, 0)), ('┡', Arms::new(2, 2, 1, 0)),
+    ('┢', Arms::new(1, 2, 2, 0)), ('┣', Arms::new(2, 2, 2, 0)), ('╞', Arms::new(1, 3, 1, 0)),
+    ('╟', Arms::new(3, 1, 3, 0)), ('╠', Arms::new(3, 3, 3, 0)),
+
+    ('┤', Arms::new(1, 0, 1, 1)), ('┥', Arms::new(1, 0, 1, 2)), ('┦', Arms::new(2, 0, 1, 1)),
+    ('┧', Arms::new(1, 0, 2, 1)), ('┨', Arms::new(2, 0, 2, 1)), ('┩', Arms::new(2, 0, 1, 2)),
+    ('┪', Arms::new(1, 0, 2, 2)), ('┫', Arms::new(2, 0, 2, 2)), ('╡', Arms::new(1, 0, 1, 3)),
+    ('╢', Arms::new(3, 0, 3, 1)), ('╣', Arms::new(3, 0, 3, 3)),
+
+    ('┬', Arms::new(0, 1, 1, 1)), ('┭', Arms::new(0, 1, 1, 2)), ('┮', Arms::new(0, 2, 1, 1)),
+    ('┯', Arms::new(0, 2, 1, 2)), ('┰', Arms::new(0, 1, 2, 1)), ('┱', Arms::new(0, 1, 2, 2)),
+    ('┲', Arms::new(0, 2, 2, 1)), ('┳', Arms::new(0, 2, 2, 2)), ('╤', Arms::new(0, 3, 1, 3)),
+    ('╥', Arms::new(0, 1, 3, 1)), ('╦', Arms::new(0, 3, 3, 3)),
+
+    ('┴', Arms::new(1, 1, 0, 1)), ('┵', Arms::new(1, 1, 0, 2)), ('┶', Arms::new(1, 2, 0, 1)),
+    ('┷', Arms::new(1, 2, 0, 2)), ('┸', Arms::new(2, 1, 0, 1)), ('┹', Arms::new(2, 1, 0, 2)),
+    ('┺', Arms::new(2, 2, 0, 1)), ('┻', Arms::new(2, 2, 0, 2)), ('╧', Arms::new(1, 3, 0, 3)),
+    ('╨', Arms::new(3, 1, 0, 1)), ('╩', Arms::new(3, 3, 0, 3)),
+
+    ('┼', Arms::new(1, 1, 1, 1)), ('┽', Arms::new(1, 1, 1, 2)), ('┾', Arms::new(1, 2, 1, 1)),
+    ('┿', Arms::new(1, 2, 1, 2)), ('╀', Arms::new(2, 1, 1, 1)), ('╁', Arms::new(1, 1, 2, 1)),
+    ('╂', Arms::new(2, 1, 2, 1)), ('╃', Arms::new(2, 1, 1, 2)), ('╄', Arms::new(2, 2, 1, 1)),
+    ('╅', Arms::new(1, 1, 2, 2)), ('╆', Arms::new(1, 2, 2, 1)), ('╇', Arms::new(2, 2, 1, 2)),
+    ('╈', Arms::new(1, 2, 2, 2)), ('╉', Arms::new(2, 1, 2, 2)), ('╊', Arms::new(2, 2, 2, 1)),
+    ('╋', Arms::new(2, 2, 2, 2)), ('╪', Arms::new(1, 3, 1, 3)), ('╫', Arms::new(3, 1, 3, 1)),
+    ('╬', Arms::new(3, 3, 3, 3)),
+];
+
+/// Light arc corners, used by rounded strokes.
+const ARCS: &[(char, Arms)] = &[
+    ('╭', Arms::new(0, 1, 1, 0)),
+    ('╮', Arms::new(0, 0, 1, 1)),
+    ('╯', Arms::new(1, 0, 0, 1)),
+    ('╰', Arms::new(1, 1, 0, 0)),
+];
+
+/// Dashed and dotted straight lines.
+#[rustfmt::skip]
+const DASHES: &[(char, Arms)] = &[
+    ('╌', Arms::new(0, 1, 0, 1)), ('╍', Arms::new(0, 2, 0, 2)),
+    ('╎', Arms::new(1, 0, 1, 0)), ('╏', Arms::new(2, 0, 2, 0)),
+    ('┄', Arms::new(0, 1, 0, 1)), ('┅', Arms::new(0, 2, 0, 2)),
+    ('┆', Arms::new(1, 0, 1, 0)), ('┇', Arms::new(2, 0, 2, 0)),
+    ('┈', Arms::new(0, 1, 0, 1)), ('┉', Arms::new(0, 2, 0, 2)),
+    ('┊', Arms::new(1, 0, 1, 0)), ('┋', Arms::new(2, 0, 2, 0)),
+];
+
+/// The pattern of a line.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Pattern {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl Pattern {
+    /// Returns `true` if the `k`th cell along a line with the provided `slope` should be
+    /// left blank.
+    ///
+    /// Only glyphs that aren't already broken up need gaps: diagonals, and horizontal
+    /// dashes in ascii.
+    pub(crate) fn skips(self, glyphs: Glyphs, slope: XY<isize>, k: usize) -> bool {
+        let gapped = match (self, glyphs) {
+            (Self::Solid, _) => false,
+            (Self::Dashed, Glyphs::Ascii) if slope.y == 0 => true,
+            _ => Arms::toward(slope).is_none(),
+        };
+
+        gapped && k % 2 == 1
+    }
+}
+
+/// A mirroring or rotation of part of the canvas, which directional glyphs are remapped
+/// along with.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub(crate) enum Transform {
+    /// Mirror left to right.
+    FlipH,
+    /// Mirror top to bottom.
+    FlipV,
+    /// Rotate 90° clockwise.
+    Rotate,
+}
+
+impl Transform {
+    /// Returns the glyph that `c` becomes under this transformation.
+    pub(crate) fn glyph(self, c: char) -> char {
+        let cycle: &[char] = match (self, c) {
+            // every transformation turns a diagonal the other way
+            (_, '/' | '\\') => &['/', '\\'],
+            (_, '╱' | '╲') => &['╱', '╲'],
+            (Self::FlipH, '<' | '>') => &['<', '>'],
+            (Self::FlipH, '◀' | '▶') => &['◀', '▶'],
+            (Self::FlipH, '◁' | '▷') => &['◁', '▷'],
+            (Self::FlipH, '(' | ')') => &['(', ')'],
+            (Self::FlipH, '[' | ']') => &['[', ']'],
+            (Self::FlipH, '{' | '}') => &['{', '}'],
+            (Self::FlipV, '^' | 'v') => &['^', 'v'],
+            (Self::FlipV, '▲' | '▼') => &['▲', '▼'],
+            (Self::FlipV, '△' | '▽') => &['△', '▽'],
+            (Self::Rotate, '^' | '>' | 'v' | '<') => &['^', '>', 'v', '<'],
+            (Self::Rotate, '▲' | '▶' | '▼' | '◀') => &['▲', '▶', '▼', '◀'],
+            (Self::Rotate, '△' | '▷' | '▽' | '◁') => &['△', '▷', '▽', '◁'],
+            _ => &[],
+        };
+
+        if let Some(i) = cycle.iter().position(|&g| g == c) {
+            return cycle[(i + 1) % cycle.len()];
+        }
+
+        // dashed lines come in pairs of horizontal glyphs followed by vertical ones
+        if let Some(i) = DASHES.iter().position(|&(g, _)| g == c) {
+            return match self {
+                Self::Rotate => DASHES[i ^ 2].0,
+                _ => c,
+            };
+        }
+
+        let arms = match Arms::of(c) {
+            Some(arms) => self.arms(arms),
+            None => return c,
+        };
+
+        match c {
+            '-' | '|' => Glyphs::Ascii.line(arms, false),
+            _ => find(ARCS, arms)
+                .filter(|_| ARCS.iter().any(|&(g, _)| g == c))
+                .or_else(|| find(BOX, arms))
+                .unwrap_or(c),
+        }
+    }
+
+    /// Returns the glyph that `c` becomes under this transformation, where `near` returns
+    /// the glyph at an offset from `c`, if there is one.
+    ///
+    /// Some ascii glyphs only have a direction in context: `.` and `'` are the corners of
+    /// rounded boxes when they join box edges, and a `-` with a blank to one side and
+    /// another `-` beyond that is part of a dashed line.
+    pub(crate) fn glyph_near<F>(self, c: char, near: F) -> char
+    where
+        F: Fn(isize, isize) -> Option<char>,
+    {
+        let h = |dx| near(dx, 0).is_some_and(|c| "-=#+".contains(c));
+        let v = |dy| near(0, dy).is_some_and(|c| "|:#+".contains(c));
+
+        let corner = match c {
+            '.' if v(1) => Some(Arms::S),
+            '\'' if v(-1) => Some(Arms::N),
+            _ => None,
+        };
+
+        let side = match (h(-1), h(1)) {
+            (_, true) => Some(Arms::E),
+            (true, _) => Some(Arms::W),
+            _ => None,
+        };
+
+        if let (Some(corner), Some(side)) = (corner, side) {
+            return match self.arms(corner | side) & Arms::S {
+                Arms::NONE => '\'',
+                _ => '.',
+            };
+        }
+
+        let dashed = [-1, 1]
+            .iter()
+            .any(|&dx| near(dx, 0).is_none() && near(dx * 2, 0) == Some('-'));
+
+        match (self, c) {
+            (Self::Rotate, ':') => '-',
+            (Self::Rotate, '-') if dashed => ':',
+            _ => self.glyph(c),
+        }
+    }
+
+    /// Returns `arms` under this transformation.
+    fn arms(self, arms: Arms) -> Arms {
+        let arm = |i: u8| arms.0 >> (i * 2) & 0b11;
+        let (n, e, s, w) = (arm(0), arm(1), arm(2), arm(3));
+
+        match self {
+            Self::FlipH => Arms::new(n, w, s, e),
+            Self::FlipV => Arms::new(s, e, n, w),
+            Self::Rotate => Arms::new(w, n, e, s),
+        }
+    }
+}
+
+/// The shape drawn at the end of an arrow.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) enum Arrowhead {
+    #[default]
+    Tip,
+    Aggregation,
+    Composition,
+    Inheritance,
+}
+
+impl Arrowhead {
+    /// Returns the glyphs of this arrowhead when pointing in the direction of `arms`,
+    /// starting at the point and going back along the line.
+    pub(crate) fn glyphs(self, glyphs: Glyphs, arms: Arms) -> Vec<char> {
+        match (self, glyphs, arms) {
+            (Self::Aggregation, Glyphs::Ascii, _) => vec!['o'],
+            (Self::Aggregation, Glyphs::Unicode, _) => vec!['◇'],
+            (Self::Composition, Glyphs::Ascii, _) => vec!['*'],
+            (Self::Composition, Glyphs::Unicode, _) => vec!['◆'],
+
+            (Self::Inheritance, Glyphs::Ascii, Arms::N) => vec!['^', '-'],
+            (Self::Inheritance, Glyphs::Ascii, Arms::E) => vec!['>', '|'],
+            (Self::Inheritance, Glyphs::Ascii, Arms::S) => vec!['v', '-'],
+            (Self::Inheritance, Glyphs::Ascii, Arms::W) => vec!['<', '|'],
+            (Self::Inheritance, Glyphs::Unicode, Arms::N) => vec!['△'],
+            (Self::Inheritance, Glyphs::Unicode, Arms::E) => vec!['▷'],
+            (Self::Inheritance, Glyphs::Unicode, Arms::S) => vec!['▽'],
+            (Self::Inheritance, Glyphs::Unicode, Arms::W) => vec!['◁'],
+
+            _ => vec![glyphs.tip(arms)],
+        }
+    }
+}
+
+/// How lines are drawn.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub(crate) struct Stroke {
+    pub(crate) glyphs: Glyphs,
+    pub(crate) weight: Weight,
+    pub(crate) rounded: bool,
+    pub(crate) pattern: Pattern,
+}
+
+impl Stroke {
+    /// Returns the stroke of a box with the top left `corner`, followed by `edge`.
+    pub(crate) fn detect(corner: char, edge: char) -> Self {
+        let glyphs = if corner.is_ascii() {
+            Glyphs::Ascii
+        } else {
+            Glyphs::Unicode
+        };
+
+        let weight = match edge {
+            '=' => Weight::Double,
+            '#' => Weight::Heavy,
+            c => Arms::of(c).map_or(Weight::Light, Arms::heaviest),
+        };
+
+        Self {
+            glyphs,
+            weight,
+            rounded: corner == '.' || ARCS.iter().any(|(c, _)| *c == corner),
+            pattern: Pattern::Solid,
+        }
+    }
+
+    /// Returns the glyph that connects to its neighbors in the directions of `arms`.
+    /// Straight lines follow the stroke's pattern, while everything else is solid.
+    pub(crate) fn glyph(self, arms: Arms) -> char {
+        let c = self.glyphs.line(arms, self.rounded);
+
+        match (self.pattern, c) {
+            (Pattern::Dashed, '|') => ':',
+            (Pattern::Dashed, '─') => '╌',
+            (Pattern::Dashed, '━') => '╍',
+            (Pattern::Dashed, '│') => '╎',
+            (Pattern::Dashed, '┃') => '╏',
+
+            (Pattern::Dotted, '-') | (Pattern::Dotted, '|') => '.',
+            (Pattern::Dotted, '─') => '┄',
+            (Pattern::Dotted, '━') => '┅',
+            (Pattern::Dotted, '│') => '┆',
+            (Pattern::Dotted, '┃') => '┇',
+
+            (_, c) => c,
+        }
+    }
+}
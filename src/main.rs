@@ -4,31 +4,26 @@
 // copied, modified, or distributed except according to those terms.
 //! TUI based ASCII diagram editor.
 // # TODO Features
-// - shapes (diamond, hexagon, parallelogram, trapezoid)
-// - resize tool
-// - box with text header area
-// - unicode
 // - maximum canvas width
-// - banner style text
 //
 // # TODO Enhancements
-// - atomically write files (w/ backup)
-// - only store deltas in undo history
-// - store undo history in a file
-// - display status changes in modeline (saved, opened, trimmed, clipped, etc)
 //
 // # TODO Correctness
-// - think of a way to do tests (dummy backend + injected events?)
-// - performance of a* is abysmal across large distances
 #![allow(clippy::many_single_char_names)]
 mod editor;
+mod figlet;
+mod glyphs;
 mod modeline;
+#[cfg(test)]
+mod tests;
 mod tools;
 mod ui;
 
 use editor::*;
+use figlet::Font;
+use glyphs::{Arrowhead, Glyphs, Pattern};
 use modeline::*;
-use tools::{PathMode::*, *};
+use tools::{BoxStyle::*, PathMode::*, *};
 use ui::*;
 
 use cursive::{
@@ -41,8 +36,19 @@ use cursive::{
     Cursive,
 };
 use cursive_buffered_backend::BufferedBackend;
-use log::debug;
-use std::{env, error::Error, path::PathBuf};
+use log::{debug, warn};
+use std::{
+    env,
+    error::Error,
+    io,
+    path::PathBuf,
+    sync::{
+        mpsc::{self, RecvTimeoutError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
 use structopt::StructOpt;
 
 #[derive(Clone, Debug, StructOpt)]
@@ -56,6 +62,50 @@ struct Options {
     #[structopt(skip = PathMode::Snap90)]
     path_mode: PathMode,
 
+    /// The pattern of lines drawn by Line and Arrow tools.
+    #[structopt(skip = Pattern::Solid)]
+    line_pattern: Pattern,
+
+    /// The head drawn at the end of arrows.
+    #[structopt(skip = Arrowhead::Tip)]
+    arrowhead: Arrowhead,
+
+    /// Draw arrowheads on both ends of arrows.
+    #[structopt(skip)]
+    double_ended: bool,
+
+    /// The character used by the Fill and Brush tools.
+    #[structopt(skip = '.')]
+    paint: char,
+
+    /// Prompt for a label after drawing with Line and Arrow tools.
+    #[structopt(skip)]
+    line_labels: bool,
+
+    /// How boxes are drawn.
+    #[structopt(skip = BoxStyle::Square)]
+    box_style: BoxStyle,
+
+    /// The number of compartments in boxes drawn by the Header Box tool.
+    #[structopt(skip = 2usize)]
+    box_sections: usize,
+
+    /// Glyph set used to draw lines (ascii or unicode).
+    #[structopt(short, long, default_value = "ascii")]
+    glyphs: Glyphs,
+
+    /// FIGlet font (.flf) used by the Banner tool.
+    #[structopt(long, parse(try_from_str = open_font))]
+    font: Option<Arc<Font>>,
+
+    /// Seconds between autosaves of unsaved changes (0 disables autosave).
+    #[structopt(long, default_value = "30")]
+    autosave: u64,
+
+    /// Keep a backup of the prior version of the file (with a ~ suffix) on save.
+    #[structopt(short, long)]
+    backup: bool,
+
     /// Keep trailing whitespace (on save).
     #[structopt(short, long)]
     keep_trailing_ws: bool,
@@ -64,17 +114,85 @@ struct Options {
     #[structopt(short, long)]
     strip_margin_ws: bool,
 
+    /// Copy and paste within askii only, leaving the system clipboard alone.
+    #[structopt(long)]
+    no_clipboard: bool,
+
+    /// Directory that unsaved changes are autosaved to [default:
+    /// $XDG_DATA_HOME/askii/recovery].
+    #[structopt(long, parse(from_os_str))]
+    recovery_dir: Option<PathBuf>,
+
     /// Text file to operate on.
     #[structopt(name = "FILE")]
     file: Option<PathBuf>,
 }
 
+fn open_font(path: &str) -> io::Result<Arc<Font>> {
+    Font::open(path).map(Arc::new)
+}
+
 impl Options {
     fn cycle_path_mode(&mut self) {
         self.path_mode = match self.path_mode {
             Snap90 => Snap45,
             Snap45 => Routed,
-            Routed => Snap90,
+            Routed => RoutedOrthogonal,
+            RoutedOrthogonal => Snap90,
+        };
+    }
+
+    fn cycle_line_pattern(&mut self) {
+        self.line_pattern = match self.line_pattern {
+            Pattern::Solid => Pattern::Dashed,
+            Pattern::Dashed => Pattern::Dotted,
+            Pattern::Dotted => Pattern::Solid,
+        };
+    }
+
+    fn cycle_arrowhead(&mut self) {
+        self.arrowhead = match self.arrowhead {
+            Arrowhead::Tip => Arrowhead::Aggregation,
+            Arrowhead::Aggregation => Arrowhead::Composition,
+            Arrowhead::Composition => Arrowhead::Inheritance,
+            Arrowhead::Inheritance => Arrowhead::Tip,
+        };
+    }
+
+    fn toggle_double_ended(&mut self) {
+        self.double_ended = !self.double_ended;
+    }
+
+    fn cycle_paint(&mut self) {
+        self.paint = match self.paint {
+            '.' => '#',
+            '#' => '░',
+            '░' => '▒',
+            _ => '.',
+        };
+    }
+
+    fn cycle_box_style(&mut self) {
+        self.box_style = match self.box_style {
+            Square => Rounded,
+            Rounded => Double,
+            Double => Heavy,
+            Heavy => Square,
+        };
+    }
+
+    fn toggle_line_labels(&mut self) {
+        self.line_labels = !self.line_labels;
+    }
+
+    fn toggle_backup(&mut self) {
+        self.backup = !self.backup;
+    }
+
+    fn cycle_glyphs(&mut self) {
+        self.glyphs = match self.glyphs {
+            Glyphs::Ascii => Glyphs::Unicode,
+            Glyphs::Unicode => Glyphs::Ascii,
         };
     }
 }
@@ -83,6 +201,46 @@ const EDITOR_ID: &str = "editor";
 const S90: &str = "Snap90";
 const S45: &str = "Snap45";
 const RTD: &str = "Routed";
+const RTO: &str = "Routed90";
+const LBL: &str = "(L) Toggle Labels";
+const TIP: &str = "Tip";
+const AGG: &str = "Aggregation";
+const CMP: &str = "Composition";
+const INH: &str = "Inheritance";
+const SQR: &str = "Square";
+const RND: &str = "Rounded";
+const DBL: &str = "Double";
+const HVY: &str = "Heavy";
+const HDR: &str = "Header";
+const CLS: &str = "Class";
+
+/// Characters offered by the paint character picker.
+const PAINTS: &[(char, &str)] = &[
+    ('.', "Dot"),
+    ('#', "Hash"),
+    ('*', "Asterisk"),
+    ('+', "Plus"),
+    ('o', "Circle"),
+    ('x', "Cross"),
+    ('~', "Tilde"),
+    ('=', "Equals"),
+    ('·', "Middle Dot"),
+    ('•', "Bullet"),
+    ('░', "Light Shade"),
+    ('▒', "Medium Shade"),
+    ('▓', "Dark Shade"),
+    ('█', "Full Block"),
+    ('▀', "Upper Half Block"),
+    ('▄', "Lower Half Block"),
+    ('■', "Square"),
+    ('●', "Disc"),
+    ('◆', "Diamond"),
+    ('─', "Horizontal Line"),
+    ('│', "Vertical Line"),
+    ('═', "Double Horizontal Line"),
+    ('║', "Double Vertical Line"),
+    ('╳', "Diagonal Cross"),
+];
 
 fn main() -> Result<(), Box<dyn Error>> {
     logger::init();
@@ -91,10 +249,11 @@ fn main() -> Result<(), Box<dyn Error>> {
     let opts = Options::from_args();
     debug!("{:?}", opts);
 
+    let autosave = opts.autosave;
     let editor = EditorView::new(Editor::open(opts)?);
     let mut siv = Cursive::try_new(|| {
         CrossTerm::init()
-            .map(|cross| BufferedBackend::new(cross))
+            .map(BufferedBackend::new)
             .map(|buf| -> Box<dyn Backend> { Box::new(buf) })
     })?;
 
@@ -110,6 +269,7 @@ fn main() -> Result<(), Box<dyn Error>> {
                 .leaf("(S) Save As", editor_save_as)
                 .leaf("(c) Clip", editor_clip)
                 .leaf("(C) Clip Prefix", editor_clip_prefix)
+                .leaf("(A) Toggle Backups", editor_toggle_backup)
                 .delimiter()
                 .leaf("(`) Debug", Cursive::toggle_debug_console)
                 .leaf("(q) Quit", editor_quit),
@@ -123,27 +283,106 @@ fn main() -> Result<(), Box<dyn Error>> {
         )
         .add_leaf("Help", editor_help)
         .add_delimiter()
-        .add_leaf("Box", editor_tool::<BoxTool, _>(|_| ()))
+        .add_subtree(
+            "Box",
+            MenuTree::new()
+                .leaf(SQR, editor_tool::<BoxTool, _>(|o| o.box_style = Square))
+                .leaf(RND, editor_tool::<BoxTool, _>(|o| o.box_style = Rounded))
+                .leaf(DBL, editor_tool::<BoxTool, _>(|o| o.box_style = Double))
+                .leaf(HVY, editor_tool::<BoxTool, _>(|o| o.box_style = Heavy))
+                .delimiter()
+                .leaf(HDR, editor_tool::<HeaderBoxTool, _>(|o| o.box_sections = 2))
+                .leaf(CLS, editor_tool::<HeaderBoxTool, _>(|o| o.box_sections = 3))
+                .delimiter()
+                .leaf("Table", editor_tool::<TableTool, _>(|_| ())),
+        )
         .add_subtree(
             "Line",
             MenuTree::new()
                 .leaf(S90, editor_tool::<LineTool, _>(|o| o.path_mode = Snap90))
                 .leaf(S45, editor_tool::<LineTool, _>(|o| o.path_mode = Snap45))
-                .leaf(RTD, editor_tool::<LineTool, _>(|o| o.path_mode = Routed)),
+                .leaf(RTD, editor_tool::<LineTool, _>(|o| o.path_mode = Routed))
+                .leaf(
+                    RTO,
+                    editor_tool::<LineTool, _>(|o| o.path_mode = RoutedOrthogonal),
+                )
+                .delimiter()
+                .leaf(LBL, editor_toggle_line_labels),
         )
         .add_subtree(
             "Arrow",
             MenuTree::new()
                 .leaf(S90, editor_tool::<ArrowTool, _>(|o| o.path_mode = Snap90))
                 .leaf(S45, editor_tool::<ArrowTool, _>(|o| o.path_mode = Snap45))
-                .leaf(RTD, editor_tool::<ArrowTool, _>(|o| o.path_mode = Routed)),
+                .leaf(RTD, editor_tool::<ArrowTool, _>(|o| o.path_mode = Routed))
+                .leaf(
+                    RTO,
+                    editor_tool::<ArrowTool, _>(|o| o.path_mode = RoutedOrthogonal),
+                )
+                .delimiter()
+                .leaf(
+                    TIP,
+                    editor_tool::<ArrowTool, _>(|o| o.arrowhead = Arrowhead::Tip),
+                )
+                .leaf(
+                    AGG,
+                    editor_tool::<ArrowTool, _>(|o| o.arrowhead = Arrowhead::Aggregation),
+                )
+                .leaf(
+                    CMP,
+                    editor_tool::<ArrowTool, _>(|o| o.arrowhead = Arrowhead::Composition),
+                )
+                .leaf(
+                    INH,
+                    editor_tool::<ArrowTool, _>(|o| o.arrowhead = Arrowhead::Inheritance),
+                )
+                .leaf(
+                    "(V) Toggle Double-Ended",
+                    editor_tool::<ArrowTool, _>(Options::toggle_double_ended),
+                )
+                .delimiter()
+                .leaf(LBL, editor_toggle_line_labels),
+        )
+        .add_subtree(
+            "Shape",
+            MenuTree::new()
+                .leaf("Diamond", editor_tool::<DiamondTool, _>(|_| ()))
+                .leaf("Hexagon", editor_tool::<HexagonTool, _>(|_| ()))
+                .leaf("Parallelogram", editor_tool::<ParallelogramTool, _>(|_| ()))
+                .leaf("Trapezoid", editor_tool::<TrapezoidTool, _>(|_| ())),
         )
         .add_leaf("Text", editor_tool::<TextTool, _>(|_| ()))
+        .add_subtree(
+            "Banner",
+            MenuTree::new()
+                .leaf("Block", editor_tool::<BannerTool, _>(|o| o.font = None))
+                .leaf("Load Font", editor_load_font),
+        )
+        .add_subtree(
+            "Fill",
+            MenuTree::new()
+                .leaf("Dots (.)", editor_tool::<FillTool, _>(|o| o.paint = '.'))
+                .leaf("Hashes (#)", editor_tool::<FillTool, _>(|o| o.paint = '#'))
+                .leaf(
+                    "Light Shade (░)",
+                    editor_tool::<FillTool, _>(|o| o.paint = '░'),
+                )
+                .leaf(
+                    "Medium Shade (▒)",
+                    editor_tool::<FillTool, _>(|o| o.paint = '▒'),
+                )
+                .delimiter()
+                .leaf("(I) Pick Character", editor_pick_paint)
+                .leaf("(i) Eyedropper", editor_tool::<EyedropperTool, _>(|_| ())),
+        )
+        .add_leaf("Brush", editor_tool::<BrushTool, _>(|_| ()))
         .add_leaf("Erase", editor_tool::<EraseTool, _>(|_| ()))
-        .add_leaf("Move", editor_tool::<MoveTool, _>(|_| ()));
+        .add_leaf("Select", editor_tool::<SelectTool, _>(|_| ()))
+        .add_leaf("Move", editor_tool::<MoveTool, _>(|_| ()))
+        .add_leaf("Resize", editor_tool::<ResizeTool, _>(|_| ()));
 
-    // * * * d * f g * i j k * * * * * * * * * * v w x y z
-    // A B * D E F G H I J K L M N O P Q R * * U V W X Y Z
+    // * * * * * * * * * j k * * * * * * * * * * * * * y *
+    // * * * D E * * * * J * * M N O * Q R * * U * W X Y Z
 
     siv.set_autohide_menu(false);
     siv.add_global_callback(Key::Esc, |s| s.select_menubar());
@@ -155,6 +394,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     siv.add_global_callback('S', editor_save_as);
     siv.add_global_callback('c', editor_clip);
     siv.add_global_callback('C', editor_clip_prefix);
+    siv.add_global_callback('A', editor_toggle_backup);
     siv.add_global_callback('`', Cursive::toggle_debug_console);
     siv.add_global_callback('q', editor_quit);
 
@@ -165,12 +405,28 @@ fn main() -> Result<(), Box<dyn Error>> {
 
     // Tools
     siv.add_global_callback('b', editor_tool::<BoxTool, _>(|_| ()));
+    siv.add_global_callback('B', modify_opts(Options::cycle_box_style));
+    siv.add_global_callback('H', editor_tool::<HeaderBoxTool, _>(|_| ()));
+    siv.add_global_callback('G', editor_tool::<TableTool, _>(|_| ()));
     siv.add_global_callback('l', editor_tool::<LineTool, _>(|_| ()));
     siv.add_global_callback('a', editor_tool::<ArrowTool, _>(|_| ()));
+    siv.add_global_callback('v', modify_opts(Options::cycle_arrowhead));
+    siv.add_global_callback('V', modify_opts(Options::toggle_double_ended));
     siv.add_global_callback('p', modify_opts(Options::cycle_path_mode));
+    siv.add_global_callback('d', modify_opts(Options::cycle_line_pattern));
+    siv.add_global_callback('L', editor_toggle_line_labels);
+    siv.add_global_callback('g', modify_opts(Options::cycle_glyphs));
     siv.add_global_callback('t', editor_tool::<TextTool, _>(|_| ()));
+    siv.add_global_callback('F', editor_tool::<BannerTool, _>(|_| ()));
+    siv.add_global_callback('f', editor_tool::<FillTool, _>(|_| ()));
+    siv.add_global_callback('w', editor_tool::<BrushTool, _>(|_| ()));
+    siv.add_global_callback('P', modify_opts(Options::cycle_paint));
+    siv.add_global_callback('I', editor_pick_paint);
+    siv.add_global_callback('i', editor_tool::<EyedropperTool, _>(|_| ()));
     siv.add_global_callback('e', editor_tool::<EraseTool, _>(|_| ()));
+    siv.add_global_callback('x', editor_tool::<SelectTool, _>(|_| ()));
     siv.add_global_callback('m', editor_tool::<MoveTool, _>(|_| ()));
+    siv.add_global_callback('z', editor_tool::<ResizeTool, _>(|_| ()));
 
     // Help
     siv.add_global_callback('h', editor_help);
@@ -185,16 +441,85 @@ fn main() -> Result<(), Box<dyn Error>> {
     let layout = LinearLayout::vertical()
         .child(edit_view)
         .weight(100)
-        .child(ModeLine::new(editor))
+        .child(ModeLine::new(editor.clone()))
         .weight(1);
 
     siv.add_fullscreen_layer(layout);
 
+    spawn_timer(&mut siv, autosave);
+
+    if let Some(path) = with_editor_mut(&mut siv, Editor::take_recovery) {
+        let msg = format!("Restore unsaved changes from {:?}?", path);
+        display_yesno(&mut siv, "Recover", msg, move |siv| {
+            match with_editor_mut(siv, |e| e.recover(&path)) {
+                Ok(()) => status(siv, "recovered"),
+                Err(e) => notify(siv, "recover failed", format!("{:?}", e)),
+            }
+        });
+    }
+
     siv.run();
 
+    // unsaved changes were either saved or discarded at this point
+    editor.write().discard_recovery();
+
     Ok(())
 }
 
+/// Spawn the thread that drives everything that happens on a timer: autosaves every
+/// `autosave` seconds, and redraws once status messages expire. It sleeps until the
+/// next of those is due, and is told about new status messages through `StatusTimer`.
+fn spawn_timer(siv: &mut Cursive, autosave: u64) {
+    let sink = siv.cb_sink().clone();
+    let (tx, rx) = mpsc::channel();
+    siv.set_user_data(StatusTimer(tx));
+
+    let period = Duration::from_secs(autosave);
+    let mut autosave_at = (autosave > 0).then(|| Instant::now() + period);
+    let mut expire_at: Option<Instant> = None;
+
+    thread::spawn(move || loop {
+        let next = autosave_at.into_iter().chain(expire_at).min();
+
+        let woken = match next {
+            Some(at) => rx.recv_timeout(at.saturating_duration_since(Instant::now())),
+            None => rx.recv().map_err(|_| RecvTimeoutError::Disconnected),
+        };
+
+        match woken {
+            Ok(at) => {
+                expire_at = Some(at);
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+            Err(RecvTimeoutError::Timeout) => {}
+        }
+
+        let now = Instant::now();
+        let autosave_due = autosave_at.is_some_and(|at| at <= now);
+        let expired = expire_at.is_some_and(|at| at <= now);
+
+        if autosave_due {
+            autosave_at = Some(now + period);
+        }
+        if expired {
+            expire_at = None;
+        }
+
+        // nothing is redrawn until something happens, which would leave an expired
+        // status message up, so any callback at all will do to clear it
+        let cb: Box<dyn FnOnce(&mut Cursive) + Send> = match (autosave_due, expired) {
+            (true, _) => Box::new(editor_autosave),
+            (false, true) => Box::new(|_| ()),
+            (false, false) => continue,
+        };
+
+        if sink.send(cb).is_err() {
+            break;
+        }
+    });
+}
+
 fn new_scrollview<V: View>(inner: V) -> ScrollView<V> {
     let mut scroll = ScrollView::new(inner).scroll_x(true).scroll_y(true);
     scroll.get_scroller_mut().set_scrollbar_padding((0, 0));
@@ -226,8 +551,9 @@ fn editor_open(siv: &mut Cursive) {
             }
             siv.pop_layer();
 
-            if let Err(e) = with_editor_mut(siv, |e| e.open_file(path)) {
-                notify(siv, "open failed", format!("{:?}", e));
+            match with_editor_mut(siv, |e| e.open_file(path)) {
+                Ok(()) => status(siv, "opened"),
+                Err(e) => notify(siv, "open failed", format!("{:?}", e)),
             }
         })
     });
@@ -236,7 +562,7 @@ fn editor_open(siv: &mut Cursive) {
 fn editor_save(siv: &mut Cursive) {
     match with_editor_mut(siv, Editor::save).map_err(|e| format!("{:?}", e)) {
         Ok(false) => editor_save_as(siv),
-        Ok(true) => notify(siv, "saved", ""),
+        Ok(true) => status(siv, "saved"),
         Err(e) => notify(siv, "save failed", e),
     }
 }
@@ -258,7 +584,7 @@ fn editor_save_as(siv: &mut Cursive) {
         siv.pop_layer();
 
         match with_editor_mut(siv, |e| e.save_as(path)).map_err(|e| format!("{:?}", e)) {
-            Ok(()) => notify(siv, "saved", ""),
+            Ok(()) => status(siv, "saved"),
             Err(e) => notify(siv, "save as failed", e),
         }
     });
@@ -266,7 +592,7 @@ fn editor_save_as(siv: &mut Cursive) {
 
 fn editor_clip(siv: &mut Cursive) {
     match with_editor(siv, |e| e.render_to_clipboard("")).map_err(|e| format!("{:?}", e)) {
-        Ok(()) => notify(siv, "clipped", ""),
+        Ok(()) => status(siv, "clipped"),
         Err(e) => notify(siv, "clip failed", e),
     }
 }
@@ -276,33 +602,91 @@ fn editor_clip_prefix(siv: &mut Cursive) {
         siv.pop_layer();
 
         match with_editor(siv, |e| e.render_to_clipboard(prefix)).map_err(|e| format!("{:?}", e)) {
-            Ok(()) => notify(siv, "clipped", ""),
+            Ok(()) => status(siv, "clipped"),
             Err(e) => notify(siv, "clip failed", e),
         }
     });
 }
 
+fn editor_toggle_backup(siv: &mut Cursive) {
+    let backup = with_editor_mut(siv, |e| {
+        e.mut_opts(Options::toggle_backup);
+        e.backup()
+    });
+
+    status(siv, if backup { "backups on" } else { "backups off" });
+}
+
+fn editor_toggle_line_labels(siv: &mut Cursive) {
+    let labels = with_editor_mut(siv, |e| {
+        e.mut_opts(Options::toggle_line_labels);
+        e.line_labels()
+    });
+
+    status(siv, if labels { "labels on" } else { "labels off" });
+}
+
+fn editor_pick_paint(siv: &mut Cursive) {
+    display_char_picker(siv, "Paint Character", PAINTS, |siv, c| {
+        with_editor_mut(siv, |e| e.mut_opts(|o| o.paint = c));
+        status(siv, format!("paint: {:?}", c));
+    });
+}
+
+fn editor_autosave(siv: &mut Cursive) {
+    if let Err(e) = with_editor_mut(siv, Editor::autosave) {
+        warn!("autosave failed: {}", e);
+        status(siv, format!("autosave failed: {}", e));
+    }
+}
+
 fn editor_quit(siv: &mut Cursive) {
     with_checked_editor(siv, "Quit", Cursive::quit);
 }
 
 fn editor_undo(siv: &mut Cursive) {
-    with_editor_mut(siv, Editor::undo);
+    if !with_editor_mut(siv, Editor::undo) {
+        status(siv, "nothing to undo");
+    }
 }
 
 fn editor_redo(siv: &mut Cursive) {
-    with_editor_mut(siv, Editor::redo);
+    if !with_editor_mut(siv, Editor::redo) {
+        status(siv, "nothing to redo");
+    }
 }
 
 fn editor_trim_margins(siv: &mut Cursive) {
     with_editor_mut(siv, Editor::trim_margins);
-    notify(siv, "trimmed", "");
+    status(siv, "trimmed");
+}
+
+fn editor_load_font(siv: &mut Cursive) {
+    display_form(siv, "Load Font", |siv, id, raw_path| {
+        let mut view = siv.find_name::<Dialog>(id).unwrap();
+
+        if raw_path.is_empty() {
+            view.set_title("Load Font: path is empty!");
+            return;
+        }
+
+        let font = match Font::open(raw_path) {
+            Ok(font) => Arc::new(font),
+            Err(e) => {
+                view.set_title(format!("Load Font: {}", e));
+                return;
+            }
+        };
+        siv.pop_layer();
+
+        editor_tool::<BannerTool, _>(|o| o.font = Some(Arc::clone(&font)))(siv);
+    });
 }
 
-fn editor_tool<'a, T: 'static, S: 'a>(apply: S) -> impl Fn(&mut Cursive) + 'a
+fn editor_tool<'a, T, S>(apply: S) -> impl Fn(&mut Cursive) + 'a
 where
-    T: Tool + Default,
-    S: Fn(&mut Options),
+    T: Tool + Default + 'static,
+    S: Fn(&mut Options) + 'a,
 {
     move |siv| {
         with_editor_mut(siv, |editor| {
@@ -312,9 +696,9 @@ where
     }
 }
 
-fn modify_opts<'a, S: 'a>(apply: S) -> impl Fn(&mut Cursive) + 'a
+fn modify_opts<'a, S>(apply: S) -> impl Fn(&mut Cursive) + 'a
 where
-    S: Fn(&mut Options),
+    S: Fn(&mut Options) + 'a,
 {
     move |siv| with_editor_mut(siv, |editor| editor.mut_opts(|o| apply(o)))
 }
@@ -328,17 +712,35 @@ const HELP: &str = "KEYBINDS:
     S   Save As: Save buffer to the specified path.
     c   Clip: Export buffer to the clipboard.
     C   Clip Prefix: Export buffer to the clipboard with a prefix before each line.
+    A   Toggle Backups: Keep the prior version of the file (with a ~ suffix) on save.
     `   Debug: Open the debug console.
     q   Quit: Quit without saving.
     u   Undo: Undo the last buffer modification.
     r   Redo: Redo the last undo.
     T   Trim Margins: Trim excess whitespace from all margins.
     b   Switch to the Box tool.
+    B   Cycle the style (square, rounded, double, heavy) of boxes drawn by the Box tool.
+    H   Switch to the Header Box tool.
+    G   Switch to the Table tool.
     l   Switch to the Line tool.
     a   Switch to the Arrow tool.
+    v   Cycle the head (tip, aggregation, composition, inheritance) of arrows drawn by the Arrow tool.
+    V   Toggle whether the Arrow tool draws heads on both ends of arrows.
     p   Cycle the type of path that Line and Arrow tools will draw.
+    d   Cycle the pattern (solid, dashed, dotted) of lines drawn by Line and Arrow tools.
+    L   Toggle Labels: Prompt for a label after drawing with Line and Arrow tools.
+    g   Cycle the glyph set (ascii or unicode) that drawing tools will use.
     t   Switch to the Text tool.
+    F   Switch to the Banner tool.
+    f   Switch to the Fill tool.
+    w   Switch to the Brush tool.
+    P   Cycle the character (., #, ░, ▒) that the Fill and Brush tools paint with.
+    I   Pick Character: Choose the character that the Fill and Brush tools paint with from a list, or enter any other.
+    i   Switch to the Eyedropper tool.
     e   Switch to the Erase tool.
+    x   Switch to the Select tool.
+    m   Switch to the Move tool.
+    z   Switch to the Resize tool.
     h   Help: Display this help message.
 
 NAVIGATION:
@@ -348,18 +750,41 @@ NAVIGATION:
 
     Menus are keyboard aware, too!
 
+KEYBOARD CURSOR:
+    K   Toggle the keyboard cursor, for drawing without a mouse.
+
+    While it is enabled, the arrow keys or h/j/k/l move the cursor instead of scrolling or switching tools. Space or Enter anchors the active tool at the cursor as if the left mouse button was pressed there, and pressing either again commits at the cursor as if it was released.
+
 TOOLS:
     Box   Draw boxes. Click and drag to the desired dimensions.
 
-    Line  Draw lines. Click and drag to the target position.
+    Header Box Draw boxes with a title row, and optionally further compartments (Box > Class). Click and drag to the desired dimensions, then type the title. Click anywhere to finish editing the title, or press Esc to discard it.
+
+    Table Draw tables in the style of the Box tool. Click and drag to the desired dimensions, then enter the number of rows and columns (e.g. 3x4). Type the content of each cell, which stops at its edges, pressing Tab or Shift-Tab to move to the next or previous cell. Click anywhere to finish editing, or press Esc to discard any changes to the current cell.
+
+    Line  Draw lines. Click and drag to the target position. If labels are enabled (L), enter a label once released, which is placed beside the longest segment of the line.
 
-    Arrow Draw arrows. Click and drag to the target position.
+    Arrow Draw arrows. Click and drag to the target position. The head can be a tip (>), aggregation (o), composition (*) or inheritance (|>), on one or both ends (Arrow menu, v, V). Labels work like they do for the Line tool.
+
+    Shape Draw diamonds, hexagons, parallelograms or trapezoids. Click and drag to the desired dimensions.
 
     Text  Write text. Click somewhere and type. Esc will discard the entered content, while clicking anywhere on the canvas will save it.
 
+    Banner Write large text in a FIGlet font. Works like the Text tool. Uses a built-in block font unless one is loaded from a .flf file (Banner > Load Font, or --font).
+
+    Fill  Fill an enclosed area. Click inside an area bounded by other content to fill it with the paint character (Fill menu, or P). Clicking on a filled area repaints it.
+
+    Brush Paint freehand. Click and drag to stamp the paint character (P) on every cell that the mouse passes over.
+
+    Eyedropper Pick the paint character used by the Fill and Brush tools from the canvas. Click on any character to pick it.
+
     Erase Erase things. Click and drag to form a box, everything inside of which will be erased.
 
-    Move  Move existing content. Click and drag to select an area, then click and drag from inside the area to move its content. Clicking outside of the selected area resets the selection.";
+    Select Copy, cut and paste content. Click and drag to select an area, then press Ctrl-C to copy or Ctrl-X to cut it. Press Ctrl-V to paste, then click and drag to position the pasted content and release to place it, or press Esc to cancel. Press | to flip the selected or pasted content horizontally, - to flip it vertically, or R to rotate it 90° clockwise; arrows, diagonals and box corners are turned along with it. Copied content is also put in the system clipboard, and content copied from elsewhere is pasted from there.
+
+    Move  Move existing content. Click and drag to select an area, then click and drag from inside the area to move its content. Clicking outside of the selected area resets the selection.
+
+    Resize Resize existing boxes. Click and drag a corner or edge of a box to the desired dimensions. Content inside of the box stays where it is.";
 
 fn editor_help(siv: &mut Cursive) {
     let version_str = format!("askii {}", env!("CARGO_PKG_VERSION"));
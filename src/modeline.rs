@@ -21,13 +21,32 @@ impl View for ModeLine {
             .unwrap_or("*scratch buffer*");
 
         if editor.is_dirty() {
-            p.with_color(ColorStyle::title_primary(), |p| p.print(at(1), &path));
+            p.with_color(ColorStyle::title_primary(), |p| p.print(at(1), path));
         } else {
-            p.with_color(ColorStyle::primary(), |p| p.print(at(1), &path));
+            p.with_color(ColorStyle::primary(), |p| p.print(at(1), path));
+        }
+
+        if let Some(msg) = editor.status() {
+            let x = path.chars().count() + 3;
+            p.with_color(ColorStyle::secondary(), |p| p.print(at(x), msg));
         }
 
         let tool = editor.active_tool();
-        p.print(at(p.size.x.saturating_sub(tool.len() + 1)), &tool);
+        let tool_x = p.size.x.saturating_sub(tool.chars().count() + 1);
+        p.print(at(tool_x), &tool);
+
+        let mut info = String::new();
+        if let Some(Vec2 { x, y }) = editor.pointer() {
+            info.push_str(&format!("{},{}  ", x, y));
+        }
+        if let Some(Vec2 { x, y }) = editor.drag_size() {
+            info.push_str(&format!("{}x{}  ", x, y));
+        }
+        let Vec2 { x, y } = editor.bounds();
+        info.push_str(&format!("[{}x{}]  ", x, y));
+
+        let info_x = tool_x.saturating_sub(info.chars().count());
+        p.with_color(ColorStyle::secondary(), |p| p.print(at(info_x), &info));
     }
 
     fn required_size(&mut self, size: Vec2) -> Vec2 {
@@ -0,0 +1,894 @@
+// Licensed under the Apache License, Version 2.0, <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+//! Headless tests that drive an editor with scripted events.
+//!
+//! Each test renders the buffer and compares it against a golden file in
+//! `tests/golden`. Set `ASKII_BLESS=1` to (re)write golden files from the current
+//! output instead.
+use super::{editor::*, glyphs::*, tools::*, Options, EDITOR_ID};
+use cursive::{
+    event::{Event, EventResult, Key, MouseButton::*, MouseEvent},
+    view::{Nameable, View},
+    views::ScrollView,
+    Cursive, Rect, Vec2,
+};
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+    sync::atomic::{AtomicUsize, Ordering},
+};
+use structopt::StructOpt;
+
+const VIEWPORT: (usize, usize) = (120, 40);
+
+/// An editor without a terminal, which events can be injected into.
+///
+/// Callbacks returned by the editor run against `siv`, which holds no more than the
+/// editor and whatever popups those callbacks open.
+struct Harness {
+    editor: EditorView,
+    scroll: ScrollView<EditorView>,
+    siv: Cursive,
+    recovery: TestDir,
+}
+
+impl Harness {
+    /// Returns a harness for an editor with default options and a blank buffer.
+    fn new() -> Self {
+        let recovery = TestDir::new();
+        let editor = open_editor(&recovery.0);
+
+        let editor = EditorView::new(editor);
+        let mut scroll = ScrollView::new(editor.clone())
+            .scroll_x(true)
+            .scroll_y(true);
+        scroll.layout(VIEWPORT.into());
+
+        let mut siv = Cursive::dummy();
+        siv.add_layer(ScrollView::new(editor.clone()).with_name(EDITOR_ID));
+
+        Self {
+            editor,
+            scroll,
+            siv,
+            recovery,
+        }
+    }
+
+    /// Mutate the editor's options with `apply`.
+    fn opts<F: FnOnce(&mut Options)>(&mut self, apply: F) -> &mut Self {
+        self.editor.write().mut_opts(apply);
+        self
+    }
+
+    /// Switch to the tool `T`.
+    fn tool<T: Tool + Default + 'static>(&mut self) -> &mut Self {
+        self.editor.write().set_tool(T::default());
+        self
+    }
+
+    /// Handle `event` as if it was received by the editor view, then lay it out as if a
+    /// frame was rendered.
+    fn event(&mut self, event: Event) -> &mut Self {
+        if let Some(EventResult::Consumed(Some(cb))) =
+            EditorCtx::new(&mut self.scroll).on_event(&event)
+        {
+            cb(&mut self.siv);
+        }
+        self.scroll.layout(VIEWPORT.into());
+        self
+    }
+
+    /// Returns `true` if a popup is open on top of the editor.
+    fn has_popup(&self) -> bool {
+        self.siv.screen().len() > 1
+    }
+
+    /// Type `text` into the open popup and submit it.
+    fn submit(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            self.siv.on_event(Event::Char(c));
+        }
+        self.siv.on_event(Event::Key(Key::Enter));
+        self
+    }
+
+    /// Inject a mouse event at `pos` on the canvas.
+    fn mouse<P: Into<Vec2>>(&mut self, event: MouseEvent, pos: P) -> &mut Self {
+        let pos = pos.into();
+
+        // make sure `pos` is in the viewport, otherwise it can't be relativized
+        EditorCtx::new(&mut self.scroll).scroll_to(pos, 1, 1);
+        self.scroll.layout(VIEWPORT.into());
+
+        let tl = self.scroll.content_viewport().top_left();
+
+        self.event(Event::Mouse {
+            offset: Vec2::zero(),
+            position: pos - tl,
+            event,
+        })
+    }
+
+    /// Click the left mouse button at `pos`.
+    fn click<P: Into<Vec2>>(&mut self, pos: P) -> &mut Self {
+        let pos = pos.into();
+        self.mouse(MouseEvent::Press(Left), pos)
+            .mouse(MouseEvent::Release(Left), pos)
+    }
+
+    /// Drag with the left mouse button through each of `points`.
+    fn drag(&mut self, points: &[(usize, usize)]) -> &mut Self {
+        let (first, last) = (points[0], points[points.len() - 1]);
+
+        self.mouse(MouseEvent::Press(Left), first);
+        for &pos in points {
+            self.mouse(MouseEvent::Hold(Left), pos);
+        }
+        self.mouse(MouseEvent::Release(Left), last)
+    }
+
+    /// Type `text`, where newlines press enter.
+    fn type_str(&mut self, text: &str) -> &mut Self {
+        for c in text.chars() {
+            match c {
+                '\n' => self.event(Event::Key(Key::Enter)),
+                c => self.event(Event::Char(c)),
+            };
+        }
+        self
+    }
+
+    /// Press `key`.
+    fn key(&mut self, key: Key) -> &mut Self {
+        self.event(Event::Key(key))
+    }
+
+    /// Returns the committed content of the buffer, without trailing whitespace.
+    fn render(&self) -> String {
+        let contents = self.editor.read().contents();
+        let mut lines: Vec<_> = contents.lines().map(str::trim_end).collect();
+
+        while lines.last().is_some_and(|line| line.is_empty()) {
+            lines.pop();
+        }
+
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+
+    /// Assert that the rendered buffer matches the golden file `name`.
+    fn assert_golden(&self, name: &str) {
+        let path: PathBuf = [env!("CARGO_MANIFEST_DIR"), "tests", "golden", name]
+            .iter()
+            .collect();
+
+        let actual = self.render();
+
+        if env::var_os("ASKII_BLESS").is_some() {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(&path, &actual).unwrap();
+            return;
+        }
+
+        let expected = fs::read_to_string(&path)
+            .unwrap_or_else(|e| panic!("failed to read {:?}: {}", path, e));
+
+        assert!(
+            expected == actual,
+            "{} does not match:\n--- expected\n{}--- actual\n{}",
+            name,
+            expected,
+            actual
+        );
+    }
+}
+
+/// A new, empty directory for files written by a test, which is removed when dropped.
+struct TestDir(PathBuf);
+
+impl TestDir {
+    fn new() -> Self {
+        static COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+        let n = COUNTER.fetch_add(1, Ordering::Relaxed);
+        let dir = env::temp_dir().join(format!("askii-test-{}-{}", process::id(), n));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        Self(dir)
+    }
+
+    fn join<P: AsRef<Path>>(&self, path: P) -> PathBuf {
+        self.0.join(path)
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+/// Opens an editor with default options and a blank buffer, which autosaves into
+/// `recovery` and leaves the system clipboard alone.
+fn open_editor(recovery: &Path) -> Editor {
+    let recovery = recovery.to_str().unwrap();
+    let args = ["askii", "--no-clipboard", "--recovery-dir", recovery];
+    Editor::open(Options::from_iter(&args)).unwrap()
+}
+
+#[test]
+fn box_ascii() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(1, 1), (8, 4)])
+        .drag(&[(5, 3), (12, 6)])
+        .assert_golden("box_ascii.txt");
+}
+
+#[test]
+fn box_unicode_styles() {
+    let mut h = Harness::new();
+    h.opts(|o| o.glyphs = Glyphs::Unicode).tool::<BoxTool>();
+
+    for (i, &style) in [
+        BoxStyle::Square,
+        BoxStyle::Rounded,
+        BoxStyle::Double,
+        BoxStyle::Heavy,
+    ]
+    .iter()
+    .enumerate()
+    {
+        h.opts(|o| o.box_style = style).tool::<BoxTool>();
+        h.drag(&[(i * 8, 0), (i * 8 + 6, 3)]);
+    }
+
+    // a junction between different styles
+    h.opts(|o| o.box_style = BoxStyle::Heavy).tool::<BoxTool>();
+    h.drag(&[(3, 2), (12, 6)]);
+
+    h.assert_golden("box_unicode_styles.txt");
+}
+
+#[test]
+fn header_box() {
+    Harness::new()
+        .opts(|o| o.box_sections = 3)
+        .tool::<HeaderBoxTool>()
+        .drag(&[(0, 0), (14, 8)])
+        .type_str("Editor")
+        .click((30, 30))
+        .assert_golden("header_box.txt");
+}
+
+#[test]
+fn line_snap90() {
+    Harness::new()
+        .tool::<LineTool>()
+        .drag(&[(0, 0), (10, 4)])
+        .drag(&[(0, 6), (0, 6), (10, 6)])
+        .assert_golden("line_snap90.txt");
+}
+
+#[test]
+fn line_patterns() {
+    let mut h = Harness::new();
+
+    for (i, &pattern) in [Pattern::Solid, Pattern::Dashed, Pattern::Dotted]
+        .iter()
+        .enumerate()
+    {
+        h.opts(|o| o.line_pattern = pattern).tool::<LineTool>();
+        h.drag(&[(0, i * 2), (12, i * 2)]);
+    }
+
+    h.assert_golden("line_patterns.txt");
+}
+
+#[test]
+fn arrow_snap45() {
+    Harness::new()
+        .opts(|o| o.path_mode = PathMode::Snap45)
+        .tool::<ArrowTool>()
+        .drag(&[(0, 0), (8, 4)])
+        .drag(&[(12, 4), (20, 0)])
+        .assert_golden("arrow_snap45.txt");
+}
+
+#[test]
+fn arrowheads() {
+    for &glyphs in &[Glyphs::Ascii, Glyphs::Unicode] {
+        let mut h = Harness::new();
+        h.opts(|o| {
+            o.glyphs = glyphs;
+            o.double_ended = true;
+        });
+
+        for (i, &head) in [
+            Arrowhead::Tip,
+            Arrowhead::Aggregation,
+            Arrowhead::Composition,
+            Arrowhead::Inheritance,
+        ]
+        .iter()
+        .enumerate()
+        {
+            h.opts(|o| o.arrowhead = head).tool::<ArrowTool>();
+            h.drag(&[(0, i * 2), (10, i * 2)]);
+            h.drag(&[(14 + i * 4, 0), (14 + i * 4, 6)]);
+        }
+
+        h.opts(|o| o.double_ended = false).tool::<ArrowTool>();
+        h.drag(&[(0, 8), (10, 10)]);
+
+        h.assert_golden(&format!("arrowheads_{}.txt", glyphs));
+    }
+}
+
+#[test]
+fn arrow_routed() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(6, 2), (14, 6)])
+        .opts(|o| o.path_mode = PathMode::Routed)
+        .tool::<ArrowTool>()
+        .drag(&[(0, 4), (10, 3), (20, 4)])
+        .assert_golden("arrow_routed.txt");
+}
+
+#[test]
+fn arrow_routed_orthogonal() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(6, 2), (14, 6)])
+        .opts(|o| o.path_mode = PathMode::RoutedOrthogonal)
+        .tool::<ArrowTool>()
+        .drag(&[(0, 4), (20, 4)])
+        .drag(&[(2, 10), (24, 1)])
+        .assert_golden("arrow_routed_orthogonal.txt");
+}
+
+#[test]
+fn arrow_routed_large_canvas() {
+    let mut h = Harness::new();
+    h.tool::<BoxTool>();
+    for i in 0..20 {
+        h.drag(&[(i * 25, i * 20), (i * 25 + 20, i * 20 + 15)]);
+    }
+
+    h.opts(|o| o.path_mode = PathMode::Routed)
+        .tool::<ArrowTool>()
+        .drag(&[(0, 499), (499, 0), (480, 0)]);
+
+    let render = h.render();
+    let lines: Vec<_> = render.lines().collect();
+    assert_eq!(lines[499].chars().next(), Some('+'));
+    assert_eq!(lines[0].chars().nth(480), Some('^'));
+}
+
+#[test]
+fn line_labels() {
+    let mut h = Harness::new();
+    h.opts(|o| o.line_labels = true).tool::<LineTool>();
+
+    for (points, label) in [
+        (&[(0, 1), (12, 6)], "HTTP"),
+        (&[(16, 0), (18, 10)], "gRPC"),
+        (&[(22, 0), (32, 0)], "top"),
+    ] {
+        h.drag(points).submit(label);
+    }
+
+    // a click doesn't draw a line, so there is nothing to label
+    h.click((40, 4));
+    assert!(!h.has_popup());
+    h.editor.write().undo();
+
+    // each label is undone along with its line
+    h.drag(&[(0, 12), (8, 12)]).submit("gone");
+    h.editor.write().undo();
+
+    h.assert_golden("line_labels.txt");
+}
+
+#[test]
+fn shapes() {
+    Harness::new()
+        .tool::<DiamondTool>()
+        .drag(&[(0, 0), (10, 6)])
+        .tool::<HexagonTool>()
+        .drag(&[(12, 0), (24, 6)])
+        .tool::<ParallelogramTool>()
+        .drag(&[(0, 8), (12, 12)])
+        .tool::<TrapezoidTool>()
+        .drag(&[(14, 8), (26, 12)])
+        .assert_golden("shapes.txt");
+}
+
+#[test]
+fn diamond_unicode() {
+    let mut h = Harness::new();
+    h.opts(|o| o.glyphs = Glyphs::Unicode)
+        .tool::<DiamondTool>()
+        .drag(&[(0, 0), (9, 5)])
+        .drag(&[(12, 0), (15, 7)])
+        .assert_golden("diamond_unicode.txt");
+}
+
+#[test]
+fn text() {
+    Harness::new()
+        .tool::<TextTool>()
+        .click((2, 1))
+        .type_str("hello\nworld")
+        .key(Key::Backspace)
+        .click((0, 0))
+        .assert_golden("text.txt");
+}
+
+#[test]
+fn text_discarded() {
+    Harness::new()
+        .tool::<TextTool>()
+        .click((2, 1))
+        .type_str("hello")
+        .key(Key::Esc)
+        .assert_golden("empty.txt");
+}
+
+#[test]
+fn banner() {
+    Harness::new()
+        .tool::<BannerTool>()
+        .click((0, 0))
+        .type_str("Hi!")
+        .click((0, 0))
+        .assert_golden("banner.txt");
+}
+
+#[test]
+fn fill() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (8, 4)])
+        .drag(&[(4, 2), (12, 6)])
+        .opts(|o| o.paint = '#')
+        .tool::<FillTool>()
+        .click((2, 1))
+        .opts(|o| o.paint = '░')
+        .tool::<FillTool>()
+        .click((6, 3))
+        .click((10, 5))
+        .opts(|o| o.paint = '.')
+        .tool::<FillTool>()
+        .click((2, 1))
+        .click((0, 6))
+        .assert_golden("fill.txt");
+}
+
+#[test]
+fn brush() {
+    Harness::new()
+        .opts(|o| o.paint = '#')
+        .tool::<BrushTool>()
+        .drag(&[(0, 0), (6, 3), (6, 3), (14, 0)])
+        .click((0, 5))
+        .assert_golden("brush.txt");
+}
+
+#[test]
+fn erase() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (10, 4)])
+        .tool::<EraseTool>()
+        .drag(&[(4, 0), (6, 4)])
+        .assert_golden("erase.txt");
+}
+
+#[test]
+fn move_selection() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (4, 2)])
+        .tool::<MoveTool>()
+        .drag(&[(0, 0), (4, 2)])
+        .drag(&[(2, 1), (8, 4)])
+        .assert_golden("move_selection.txt");
+}
+
+#[test]
+fn select_copy_cut_paste() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (4, 2)])
+        .tool::<SelectTool>()
+        .drag(&[(0, 0), (4, 2)])
+        .event(Event::CtrlChar('c'))
+        .event(Event::CtrlChar('v'))
+        .drag(&[(3, 3), (8, 0)])
+        .drag(&[(0, 0), (2, 2)])
+        .event(Event::CtrlChar('x'))
+        .event(Event::CtrlChar('v'))
+        .drag(&[(0, 5), (2, 5)])
+        .event(Event::CtrlChar('v'))
+        .key(Key::Esc)
+        .assert_golden("select_copy_cut_paste.txt");
+}
+
+#[test]
+fn select_flip_rotate() {
+    for &glyphs in &[Glyphs::Ascii, Glyphs::Unicode] {
+        let mut h = Harness::new();
+        h.opts(|o| o.glyphs = glyphs)
+            .tool::<BoxTool>()
+            .drag(&[(0, 0), (4, 2)])
+            .tool::<ArrowTool>()
+            .drag(&[(4, 1), (10, 1)])
+            .opts(|o| o.path_mode = PathMode::Snap45)
+            .tool::<ArrowTool>()
+            .drag(&[(2, 2), (5, 5)]);
+
+        // a mirrored copy to the right, then the original turned on its side
+        h.tool::<SelectTool>()
+            .drag(&[(0, 0), (10, 5)])
+            .event(Event::CtrlChar('c'))
+            .event(Event::CtrlChar('v'))
+            .event(Event::Char('|'))
+            .event(Event::Char('-'))
+            .drag(&[(14, 0), (14, 0)])
+            .drag(&[(0, 0), (10, 5)])
+            .event(Event::Char('R'));
+
+        h.assert_golden(&format!("select_flip_rotate_{}.txt", glyphs));
+    }
+}
+
+#[test]
+fn resize_box() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (6, 3)])
+        .tool::<TextTool>()
+        .click((2, 1))
+        .type_str("hi")
+        .click((2, 1))
+        .tool::<ResizeTool>()
+        .drag(&[(6, 3), (12, 5)])
+        .drag(&[(0, 0), (3, 1)])
+        .assert_golden("resize_box.txt");
+}
+
+#[test]
+fn undo_redo() {
+    let mut h = Harness::new();
+    h.tool::<BoxTool>()
+        .drag(&[(0, 0), (4, 2)])
+        .drag(&[(6, 0), (10, 2)]);
+
+    assert!(h.editor.write().undo());
+    h.assert_golden("undo_redo_undone.txt");
+
+    assert!(h.editor.write().redo());
+    assert!(!h.editor.write().redo());
+    h.assert_golden("undo_redo_redone.txt");
+}
+
+#[test]
+fn keyboard_cursor() {
+    let mut h = Harness::new();
+    h.tool::<BoxTool>().event(Event::Char('K'));
+
+    for c in "jl l".chars() {
+        h.event(Event::Char(c));
+    }
+    for _ in 0..6 {
+        h.key(Key::Right);
+    }
+    h.key(Key::Down).key(Key::Down).key(Key::Enter);
+
+    h.assert_golden("keyboard_cursor.txt");
+}
+
+#[test]
+fn eyedropper() {
+    Harness::new()
+        .opts(|o| o.paint = '#')
+        .tool::<BrushTool>()
+        .drag(&[(0, 0), (4, 0)])
+        .opts(|o| o.paint = '.')
+        .tool::<EyedropperTool>()
+        .click((8, 0))
+        .click((2, 0))
+        .tool::<BrushTool>()
+        .drag(&[(0, 2), (4, 2)])
+        .assert_golden("eyedropper.txt");
+}
+
+#[test]
+fn table() {
+    for glyphs in [Glyphs::Ascii, Glyphs::Unicode] {
+        let mut h = Harness::new();
+        let stroke = Stroke {
+            glyphs,
+            ..Stroke::default()
+        };
+        start_table(
+            &mut h.editor.write(),
+            stroke,
+            Rect::from_corners((0, 0), (18, 6)),
+            3,
+            3,
+        );
+
+        h.type_str("Name")
+            .key(Key::Tab)
+            .type_str("Qty")
+            .key(Key::Tab)
+            .type_str("Note")
+            .event(Event::Shift(Key::Tab))
+            .type_str("!")
+            .click((30, 30))
+            .assert_golden(&format!("table_{:?}.txt", glyphs).to_lowercase());
+    }
+}
+
+#[test]
+fn save_as_discards_scratch_recovery() {
+    let dir = TestDir::new();
+
+    let mut h = Harness::new();
+    let scratch = h.recovery.join(format!("scratch-{}", process::id()));
+    h.tool::<BoxTool>().drag(&[(0, 0), (4, 2)]);
+    h.editor.write().autosave().unwrap();
+    assert!(scratch.exists());
+
+    let path = dir.join("saved.txt");
+    h.editor.write().save_as(&path).unwrap();
+
+    assert!(!scratch.exists());
+    assert_eq!(fs::read_to_string(&path).unwrap(), h.render());
+}
+
+#[test]
+#[cfg(unix)]
+fn scratch_recovery_of_running_instance_is_not_offered() {
+    let recovery = TestDir::new();
+
+    // init is always running, while no process can have the largest id
+    let running = recovery.join("scratch-1");
+    let orphaned = recovery.join(format!("scratch-{}", u32::MAX));
+    fs::write(&running, "running\n").unwrap();
+    fs::write(&orphaned, "orphaned\n").unwrap();
+
+    let mut editor = open_editor(&recovery.0);
+    assert_eq!(editor.take_recovery(), Some(orphaned.clone()));
+
+    editor.discard_recovery();
+    assert!(!orphaned.exists());
+    assert!(running.exists());
+}
+
+#[test]
+fn table_cell_overflow() {
+    let mut h = Harness::new();
+    start_table(
+        &mut h.editor.write(),
+        Stroke::default(),
+        Rect::from_corners((0, 0), (12, 4)),
+        1,
+        2,
+    );
+
+    h.type_str("a very long cell\nsecond\nthird\nfourth")
+        .key(Key::Tab)
+        .type_str("ok")
+        .click((30, 30))
+        .assert_golden("table_cell_overflow.txt");
+}
+
+#[test]
+fn select_transform_ascii_context() {
+    let mut h = Harness::new();
+    h.opts(|o| o.box_style = BoxStyle::Rounded)
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (6, 3)])
+        .opts(|o| o.line_pattern = Pattern::Dotted)
+        .tool::<LineTool>()
+        .drag(&[(8, 0), (14, 0)])
+        .opts(|o| o.line_pattern = Pattern::Dashed)
+        .tool::<LineTool>()
+        .drag(&[(8, 2), (14, 2)])
+        .drag(&[(16, 0), (16, 4)])
+        .tool::<TextTool>()
+        .click((1, 5))
+        .type_str("the end.")
+        .click((30, 30));
+
+    // corners and dashes are remapped, while dots and text stay as they are
+    h.tool::<SelectTool>()
+        .drag(&[(0, 0), (16, 5)])
+        .event(Event::CtrlChar('c'))
+        .event(Event::CtrlChar('v'))
+        .event(Event::Char('-'))
+        .drag(&[(20, 0), (20, 0)])
+        .event(Event::CtrlChar('v'))
+        .event(Event::Char('R'))
+        .drag(&[(0, 8), (0, 8)]);
+
+    // rotating all the way around gets back to where it started
+    h.event(Event::CtrlChar('v'));
+    for _ in 0..4 {
+        h.event(Event::Char('R'));
+    }
+    h.drag(&[(20, 8), (20, 8)])
+        .assert_golden("select_transform_ascii_context.txt");
+}
+
+#[test]
+fn header_box_long_title() {
+    Harness::new()
+        .opts(|o| o.box_sections = 2)
+        .tool::<HeaderBoxTool>()
+        .drag(&[(0, 0), (8, 5)])
+        .type_str("Title that is long\nx")
+        .click((30, 30))
+        .assert_golden("header_box_long_title.txt");
+}
+
+#[test]
+fn resize_header_box() {
+    Harness::new()
+        .opts(|o| o.box_sections = 3)
+        .tool::<HeaderBoxTool>()
+        .drag(&[(0, 0), (8, 8)])
+        .type_str("Name")
+        .click((30, 30))
+        .tool::<ResizeTool>()
+        .drag(&[(8, 8), (14, 10)])
+        .drag(&[(14, 4), (18, 4)])
+        .drag(&[(18, 10), (10, 0)])
+        .assert_golden("resize_header_box.txt");
+}
+
+#[test]
+#[cfg(unix)]
+fn save_through_symlink() {
+    let dir = TestDir::new();
+    let target = dir.join("target.txt");
+    let link = dir.join("link.txt");
+    fs::write(&target, "old\n").unwrap();
+    std::os::unix::fs::symlink("target.txt", &link).unwrap();
+
+    let mut h = Harness::new();
+    h.opts(|o| o.backup = true);
+    h.editor.write().open_file(&link).unwrap();
+    h.tool::<BoxTool>().drag(&[(0, 1), (2, 2)]);
+    h.editor.write().save().unwrap();
+
+    assert!(fs::symlink_metadata(&link)
+        .unwrap()
+        .file_type()
+        .is_symlink());
+    assert_eq!(fs::read_to_string(&target).unwrap(), h.render());
+    assert_eq!(fs::read_to_string(dir.join("link.txt~")).unwrap(), "old\n");
+
+    let mut names: Vec<_> = fs::read_dir(&dir.0)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect();
+    names.sort();
+    assert_eq!(
+        names,
+        [
+            ".link.txt.askii-undo",
+            "link.txt",
+            "link.txt~",
+            "target.txt"
+        ]
+    );
+}
+
+#[test]
+fn history_survives_reopening() {
+    let dir = TestDir::new();
+    let path = dir.join("diagram.txt");
+
+    let mut h = Harness::new();
+    h.editor.write().open_file(&path).unwrap();
+    h.tool::<BoxTool>().drag(&[(0, 0), (4, 2)]);
+    let first = h.render();
+    h.drag(&[(6, 0), (10, 2)]);
+    h.editor.write().save().unwrap();
+    let second = h.render();
+
+    let h = Harness::new();
+    h.editor.write().open_file(&path).unwrap();
+    assert_eq!(h.render(), second);
+    assert!(!h.editor.read().is_dirty());
+
+    assert!(h.editor.write().undo());
+    assert_eq!(h.render(), first);
+    assert!(h.editor.write().redo());
+    assert_eq!(h.render(), second);
+}
+
+#[test]
+fn history_is_discarded_after_outside_edit() {
+    let dir = TestDir::new();
+    let path = dir.join("diagram.txt");
+
+    let mut h = Harness::new();
+    h.editor.write().open_file(&path).unwrap();
+    h.tool::<BoxTool>().drag(&[(0, 0), (4, 2)]);
+    h.editor.write().save().unwrap();
+
+    fs::write(&path, "edited elsewhere\n").unwrap();
+
+    let h = Harness::new();
+    h.editor.write().open_file(&path).unwrap();
+    assert_eq!(h.render(), "edited elsewhere\n");
+    assert!(!h.editor.write().undo());
+    assert_eq!(h.render(), "edited elsewhere\n");
+}
+
+#[test]
+fn dirty_tracking() {
+    let dir = TestDir::new();
+    let path = dir.join("diagram.txt");
+
+    let mut h = Harness::new();
+    h.editor.write().open_file(&path).unwrap();
+    h.tool::<BoxTool>().drag(&[(0, 0), (4, 2)]);
+    assert!(h.editor.read().is_dirty());
+
+    h.editor.write().save().unwrap();
+    assert!(!h.editor.read().is_dirty());
+
+    h.drag(&[(6, 0), (10, 2)]);
+    assert!(h.editor.read().is_dirty());
+
+    h.editor.write().undo();
+    assert!(!h.editor.read().is_dirty());
+
+    // the saved state is gone for good once it can't be reached by undo or redo
+    h.editor.write().undo();
+    assert!(h.editor.read().is_dirty());
+    h.drag(&[(0, 4), (4, 6)]);
+    assert!(h.editor.read().is_dirty());
+    h.editor.write().undo();
+    assert!(h.editor.read().is_dirty());
+}
+
+#[test]
+fn resize_header_box_with_flat_header() {
+    Harness::new()
+        .tool::<BoxTool>()
+        .drag(&[(0, 0), (6, 1)])
+        .drag(&[(0, 1), (6, 4)])
+        .tool::<ResizeTool>()
+        .drag(&[(3, 0), (3, 3)])
+        .drag(&[(3, 4), (3, 6)])
+        .drag(&[(6, 3), (10, 3)])
+        .assert_golden("resize_header_box_with_flat_header.txt");
+}
+
+#[test]
+fn label_over_unchanged_line() {
+    let mut h = Harness::new();
+    h.tool::<LineTool>().drag(&[(0, 2), (8, 2)]);
+    let line = h.render();
+
+    // redrawing the same line changes nothing, so the label is a step of its own
+    h.opts(|o| o.line_labels = true)
+        .tool::<LineTool>()
+        .drag(&[(0, 2), (8, 2)])
+        .submit("lbl");
+    assert_ne!(h.render(), line);
+
+    h.editor.write().undo();
+    assert_eq!(h.render(), line);
+}
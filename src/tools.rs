@@ -3,14 +3,22 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 use super::{
-    editor::{Buffer, Cell, Char, EditorCtx, CONSUMED, SP},
+    editor::{transform_cells, Buffer, Cell, Char, Editor, EditorCtx, CONSUMED, SP},
+    figlet::Font,
+    glyphs::{Arrowhead, Glyphs, Pattern, Stroke, Transform, Weight},
+    ui::{display_form, status, with_editor_mut},
     Options,
 };
 use cursive::{
     event::{Event, EventResult, Key, MouseButton::*, MouseEvent::*},
+    views::Dialog,
     Rect, Vec2,
 };
-use std::{cmp::min, fmt};
+use std::{
+    cmp::{max, min},
+    fmt, iter, mem,
+    sync::Arc,
+};
 
 macro_rules! option {
     ($a:expr) => {
@@ -49,8 +57,15 @@ macro_rules! mouse_drag {
 /// Provides an implementation of `Tool::on_event` for tools that contain a `src` and
 /// `dst` field of type `Option<Vec2>`. The implementation performs basic left mouse
 /// drag handling, calling the argument closure when relevant events occur.
+///
+/// If a second closure is provided, its result is returned once the drag is released.
+/// It is also told whether the release recorded a step in the undo history.
 macro_rules! fn_on_event_drag {
     ($render:expr) => {
+        fn_on_event_drag!($render, |_: &mut Self, _| CONSUMED);
+    };
+
+    ($render:expr, $release:expr) => {
         fn on_event(&mut self, ctx: &mut EditorCtx<'_>, event: &Event) -> Option<EventResult> {
             let (pos, event) = mouse_drag!(ctx, event);
 
@@ -68,9 +83,10 @@ macro_rules! fn_on_event_drag {
 
                 Release(Left) => {
                     self.dst = Some(pos);
-                    ctx.clobber(|buf| $render(self, buf));
+                    let recorded = ctx.clobber(|buf| $render(self, buf));
                     self.src = None;
                     self.dst = None;
+                    return $release(self, recorded);
                 }
 
                 _ => return None,
@@ -81,6 +97,27 @@ macro_rules! fn_on_event_drag {
     };
 }
 
+/// Returns the result of releasing a Line or Arrow drag through `points`, which prompts
+/// for a label if `labels` is set and the line goes anywhere. If drawing the line was
+/// `recorded` in the undo history, the label becomes part of that same step.
+fn release_line(labels: bool, points: Vec<Vec2>, recorded: bool) -> Option<EventResult> {
+    if !labels || points.iter().all(|&pos| pos == points[0]) {
+        return CONSUMED;
+    }
+
+    Some(EventResult::with_cb(move |siv| {
+        let points = points.clone();
+
+        display_form(siv, "Label", move |siv, _, label| {
+            siv.pop_layer();
+
+            if !label.is_empty() {
+                with_editor_mut(siv, |e| e.label_line(&points, label, recorded));
+            }
+        });
+    }))
+}
+
 macro_rules! simple_display {
     ($type:ty, $fstr:expr) => {
         impl fmt::Display for $type {
@@ -97,134 +134,565 @@ pub(crate) trait Tool: fmt::Display {
     fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult>;
 }
 
+#[derive(Copy, Clone, Debug, Default)]
+pub(crate) enum BoxStyle {
+    #[default]
+    Square,
+    Rounded,
+    Double,
+    Heavy,
+}
+
+impl BoxStyle {
+    fn stroke(self, glyphs: Glyphs) -> Stroke {
+        let (weight, rounded) = match self {
+            BoxStyle::Square => (Weight::Light, false),
+            BoxStyle::Rounded => (Weight::Light, true),
+            BoxStyle::Double => (Weight::Double, false),
+            BoxStyle::Heavy => (Weight::Heavy, false),
+        };
+
+        Stroke {
+            glyphs,
+            weight,
+            rounded,
+            ..Stroke::default()
+        }
+    }
+}
+
 #[derive(Copy, Clone, Default)]
 pub(crate) struct BoxTool {
     src: Option<Vec2>,
     dst: Option<Vec2>,
+    glyphs: Glyphs,
+    style: BoxStyle,
 }
 
-simple_display! { BoxTool, "Box" }
+impl fmt::Display for BoxTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Box: {:?}", self.style)
+    }
+}
 
 impl Tool for BoxTool {
+    fn load_opts(&mut self, opts: &Options) {
+        self.glyphs = opts.glyphs;
+        self.style = opts.box_style;
+    }
+
     fn_on_event_drag!(|t: &Self, buf: &mut Buffer| {
         let (src, dst) = option!(t.src, t.dst);
 
+        buf.draw_box(t.style.stroke(t.glyphs), Rect::from_corners(src, dst));
+    });
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct HeaderBoxTool {
+    src: Option<Vec2>,
+    dst: Option<Vec2>,
+    glyphs: Glyphs,
+    style: BoxStyle,
+    sections: usize,
+    title: TextTool,
+}
+
+impl fmt::Display for HeaderBoxTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Header Box: {:?} x{}", self.style, self.sections)
+    }
+}
+
+impl Tool for HeaderBoxTool {
+    fn load_opts(&mut self, opts: &Options) {
+        self.glyphs = opts.glyphs;
+        self.style = opts.box_style;
+        self.sections = opts.box_sections;
+    }
+
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        if self.title.cursor_active {
+            return self.title.on_event(ctx, e);
+        }
+
+        let (pos, event) = mouse_drag!(ctx, e);
+
+        match event {
+            Press(Left) => {
+                self.src = Some(pos);
+                self.dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Hold(Left) => {
+                self.dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            // a release can arrive without a press when the click ended title entry
+            Release(Left) if self.src.is_some() => {
+                self.dst = Some(pos);
+                ctx.clobber(|buf| self.render(buf));
+
+                let r = Rect::from_corners(self.src.unwrap(), pos);
+                if r.height() > 2 && r.width() > 2 {
+                    let title = (r.top_left() + (1, 1), (r.right() - 1, r.top() + 1));
+                    self.title
+                        .start_within(ctx, Rect::from_corners(title.0, title.1));
+                }
+
+                self.src = None;
+                self.dst = None;
+            }
+
+            _ => return None,
+        }
+
+        CONSUMED
+    }
+}
+
+impl HeaderBoxTool {
+    fn render(&self, buf: &mut Buffer) {
+        let (src, dst) = option!(self.src, self.dst);
         let r = Rect::from_corners(src, dst);
+        let stroke = self.style.stroke(self.glyphs);
+
+        buf.draw_box(stroke, r);
+
+        // the first compartment is always a single row for the title, the rest split
+        // whatever space remains evenly
+        let top = r.top() + 2;
+        let rest = r.bottom().saturating_sub(top);
+        let mut last = r.top();
+
+        for i in 0..self.sections.saturating_sub(1) {
+            let y = top + rest * i / (self.sections - 1);
+
+            if y <= last + 1 || y + 1 >= r.bottom() {
+                break;
+            }
+
+            buf.draw_line(stroke, Vec2::new(r.left(), y), Vec2::new(r.right(), y));
+            last = y;
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct TableTool {
+    src: Option<Vec2>,
+    dst: Option<Vec2>,
+    glyphs: Glyphs,
+    style: BoxStyle,
+    cells: Vec<Rect>,
+    cell: usize,
+    text: TextTool,
+}
 
-        buf.draw_line(r.top_left(), r.top_right());
-        buf.draw_line(r.top_right(), r.bottom_right());
-        buf.draw_line(r.bottom_right(), r.bottom_left());
-        buf.draw_line(r.bottom_left(), r.top_left());
+impl fmt::Display for TableTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Table: {:?}", self.style)
+    }
+}
+
+impl Tool for TableTool {
+    fn load_opts(&mut self, opts: &Options) {
+        self.glyphs = opts.glyphs;
+        self.style = opts.box_style;
+    }
+
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        if !self.cells.is_empty() {
+            return self.on_cell_event(ctx, e);
+        }
+
+        let (pos, event) = mouse_drag!(ctx, e);
+
+        match event {
+            Press(Left) => {
+                self.src = Some(pos);
+                self.dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Hold(Left) => {
+                self.dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Release(Left) if self.src.is_some() => {
+                let area = Rect::from_corners(self.src.take().unwrap(), pos);
+                self.dst = None;
+                ctx.preview(|_| ());
+
+                return release_table(self.style.stroke(self.glyphs), area);
+            }
+
+            _ => return None,
+        }
+
+        CONSUMED
+    }
+}
+
+impl TableTool {
+    fn render(&self, buf: &mut Buffer) {
+        let (src, dst) = option!(self.src, self.dst);
+
+        buf.draw_box(self.style.stroke(self.glyphs), Rect::from_corners(src, dst));
+    }
+
+    /// Handles an event while the cells of a table are being edited. Tab and shift-tab
+    /// move to the next and previous cell, anything else goes to text entry.
+    fn on_cell_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        // the table is drawn outside of any event, so entry starts with the first one
+        if !self.text.cursor_active {
+            self.text.start_within(ctx, self.cells[self.cell]);
+        }
+
+        let n = self.cells.len();
+
+        match e {
+            Event::Key(Key::Tab) => self.goto_cell(ctx, (self.cell + 1) % n),
+            Event::Shift(Key::Tab) => self.goto_cell(ctx, (self.cell + n - 1) % n),
+
+            _ => {
+                let res = self.text.on_event(ctx, e);
+
+                if !self.text.cursor_active {
+                    self.cells.clear();
+                    self.cell = 0;
+                }
+
+                return res;
+            }
+        }
+
+        CONSUMED
+    }
+
+    /// Commit the content of the current cell and start editing cell `i`.
+    fn goto_cell(&mut self, ctx: &mut EditorCtx<'_>, i: usize) {
+        ctx.clobber(|buf| self.text.render(buf));
+        self.cell = i;
+        self.text.start_within(ctx, self.cells[i]);
+        ctx.scroll_to_cursor();
+    }
+}
+
+/// Prompt for the number of rows and columns of a table over `area`, then draw it.
+fn release_table(stroke: Stroke, area: Rect) -> Option<EventResult> {
+    let fits = ((area.height() - 1) / 2, (area.width() - 1) / 2);
+    if fits.0 == 0 || fits.1 == 0 {
+        return CONSUMED;
+    }
+
+    Some(EventResult::with_cb(move |siv| {
+        display_form(siv, "Table: Rows x Columns", move |siv, id, input| {
+            let mut view = siv.find_name::<Dialog>(id).unwrap();
+
+            let (rows, cols) = match parse_table_size(input) {
+                Some(size) => size,
+                None => {
+                    view.set_title("Table: expected rows x columns!");
+                    return;
+                }
+            };
+
+            if rows > fits.0 || cols > fits.1 {
+                view.set_title(format!("Table: at most {} x {} fits!", fits.0, fits.1));
+                return;
+            }
+            siv.pop_layer();
+
+            with_editor_mut(siv, |e| start_table(e, stroke, area, rows, cols));
+        });
+    }))
+}
+
+/// Parses a table size like `3x4` or `3 4` into a number of rows and columns.
+fn parse_table_size(input: &str) -> Option<(usize, usize)> {
+    let mut dims = input
+        .split(|c: char| c == 'x' || c == 'X' || c == ',' || c.is_whitespace())
+        .filter(|dim| !dim.is_empty())
+        .map(str::parse);
+
+    match (dims.next(), dims.next(), dims.next()) {
+        (Some(Ok(rows)), Some(Ok(cols)), None) if rows > 0 && cols > 0 => Some((rows, cols)),
+        _ => None,
+    }
+}
+
+/// Draw a table of `rows` by `cols` cells over `area`, then switch to a table tool that
+/// edits its cells, starting with the first one.
+pub(crate) fn start_table(
+    editor: &mut Editor,
+    stroke: Stroke,
+    area: Rect,
+    rows: usize,
+    cols: usize,
+) {
+    let cells = editor.draw_table(stroke, area, rows, cols);
+    let first = cells[0].top_left();
+
+    editor.set_tool(TableTool {
+        cells,
+        ..TableTool::default()
     });
+    editor.set_cursor(first);
 }
 
-#[derive(Copy, Clone, Debug)]
+#[derive(Copy, Clone, Debug, Default)]
 pub(crate) enum PathMode {
+    #[default]
     Snap90,
     Snap45,
     Routed,
+    RoutedOrthogonal,
 }
 
-impl Default for PathMode {
-    fn default() -> Self {
-        Self::Snap90
-    }
-}
-
-#[derive(Copy, Clone, Default)]
+#[derive(Clone, Default)]
 pub(crate) struct LineTool {
     src: Option<Vec2>,
     dst: Option<Vec2>,
     path_mode: PathMode,
+    glyphs: Glyphs,
+    pattern: Pattern,
+    labels: bool,
+    points: Vec<Vec2>,
 }
 
 impl fmt::Display for LineTool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Line: {:?}", self.path_mode)
+        write!(f, "Line: {:?} {:?}", self.path_mode, self.pattern)
     }
 }
 
 impl Tool for LineTool {
     fn load_opts(&mut self, opts: &Options) {
         self.path_mode = opts.path_mode;
+        self.glyphs = opts.glyphs;
+        self.pattern = opts.line_pattern;
+        self.labels = opts.line_labels;
     }
 
-    fn_on_event_drag!(|t: &Self, buf: &mut Buffer| {
-        let (src, dst) = option!(t.src, t.dst);
+    fn_on_event_drag!(
+        |t: &mut Self, buf: &mut Buffer| {
+            t.points.clear();
+            let (src, dst) = option!(t.src, t.dst);
+            let stroke = t.stroke();
 
-        if let PathMode::Routed = t.path_mode {
-            buf.draw_path(src, dst);
-            return;
-        }
+            if let PathMode::Routed | PathMode::RoutedOrthogonal = t.path_mode {
+                t.points = buf.draw_path(stroke, t.path_mode, src, dst);
+                return;
+            }
 
-        let mid = match t.path_mode {
-            PathMode::Snap90 => buf.snap90(src, dst),
-            _ => buf.snap45(src, dst),
-        };
+            let mid = match t.path_mode {
+                PathMode::Snap90 => buf.snap90(src, dst),
+                _ => buf.snap45(src, dst),
+            };
 
-        buf.draw_line(src, mid);
-        buf.draw_line(mid, dst);
-    });
+            buf.draw_polyline(stroke, &[src, mid, dst]);
+            t.points = vec![src, mid, dst];
+        },
+        |t: &mut Self, recorded| release_line(t.labels, mem::take(&mut t.points), recorded)
+    );
 }
 
-#[derive(Copy, Clone, Default)]
+impl LineTool {
+    fn stroke(&self) -> Stroke {
+        Stroke {
+            glyphs: self.glyphs,
+            pattern: self.pattern,
+            ..Stroke::default()
+        }
+    }
+}
+
+#[derive(Clone, Default)]
 pub(crate) struct ArrowTool {
     src: Option<Vec2>,
     dst: Option<Vec2>,
     path_mode: PathMode,
+    glyphs: Glyphs,
+    pattern: Pattern,
+    head: Arrowhead,
+    double_ended: bool,
+    labels: bool,
+    points: Vec<Vec2>,
 }
 
 impl fmt::Display for ArrowTool {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Arrow: {:?}", self.path_mode)
+        let ends = if self.double_ended { " x2" } else { "" };
+
+        write!(
+            f,
+            "Arrow: {:?} {:?} {:?}{}",
+            self.path_mode, self.pattern, self.head, ends
+        )
     }
 }
 
 impl Tool for ArrowTool {
     fn load_opts(&mut self, opts: &Options) {
         self.path_mode = opts.path_mode;
+        self.glyphs = opts.glyphs;
+        self.pattern = opts.line_pattern;
+        self.head = opts.arrowhead;
+        self.double_ended = opts.double_ended;
+        self.labels = opts.line_labels;
     }
 
-    fn_on_event_drag!(|t: &Self, buf: &mut Buffer| {
-        let (src, dst) = option!(t.src, t.dst);
+    fn_on_event_drag!(
+        |t: &mut Self, buf: &mut Buffer| {
+            t.points.clear();
+            let (src, dst) = option!(t.src, t.dst);
+            let stroke = t.stroke();
 
-        if let PathMode::Routed = t.path_mode {
-            let last = buf.draw_path(src, dst);
-            buf.draw_arrow_tip(last, dst);
-            return;
+            if let PathMode::Routed | PathMode::RoutedOrthogonal = t.path_mode {
+                let path = buf.draw_path(stroke, t.path_mode, src, dst);
+                if t.double_ended {
+                    buf.draw_arrow_tip(t.glyphs, t.head, path[min(1, path.len() - 1)], src);
+                }
+                buf.draw_arrow_tip(t.glyphs, t.head, path[path.len().saturating_sub(2)], dst);
+                t.points = path;
+                return;
+            }
+
+            let mid = match t.path_mode {
+                PathMode::Snap90 => buf.snap90(src, dst),
+                _ => buf.snap45(src, dst),
+            };
+
+            buf.draw_polyline(stroke, &[src, mid, dst]);
+
+            if t.double_ended {
+                let next = if mid != src { mid } else { dst };
+                buf.draw_arrow_tip(t.glyphs, t.head, next, src);
+            }
+
+            let last = if mid != dst { mid } else { src };
+            buf.draw_arrow_tip(t.glyphs, t.head, last, dst);
+            t.points = vec![src, mid, dst];
+        },
+        |t: &mut Self, recorded| release_line(t.labels, mem::take(&mut t.points), recorded)
+    );
+}
+
+impl ArrowTool {
+    fn stroke(&self) -> Stroke {
+        Stroke {
+            glyphs: self.glyphs,
+            pattern: self.pattern,
+            ..Stroke::default()
         }
+    }
+}
 
-        let mid = match t.path_mode {
-            PathMode::Snap90 => buf.snap90(src, dst),
-            _ => buf.snap45(src, dst),
-        };
+/// Provides a `Tool` implementation for a shape that is drawn as a closed polygon through
+/// the vertices that `$vertices` returns for the area formed by dragging, or by `$draw`
+/// for shapes that aren't polygons.
+macro_rules! shape_tool {
+    ($type:ident, $name:expr, $vertices:expr) => {
+        shape_tool!($type, $name, draw: |buf: &mut Buffer, stroke: Stroke, r: Rect| {
+            let mut points: Vec<Vec2> = $vertices(r);
+            points.push(points[0]);
+
+            buf.draw_polyline(stroke, &points);
+        });
+    };
 
-        if mid != dst {
-            buf.draw_line(src, mid);
-            buf.draw_line(mid, dst);
-            buf.draw_arrow_tip(mid, dst);
-        } else {
-            buf.draw_line(src, dst);
-            buf.draw_arrow_tip(src, dst);
+    ($type:ident, $name:expr, draw: $draw:expr) => {
+        #[derive(Copy, Clone, Default)]
+        pub(crate) struct $type {
+            src: Option<Vec2>,
+            dst: Option<Vec2>,
+            glyphs: Glyphs,
         }
-    });
+
+        simple_display! { $type, $name }
+
+        impl Tool for $type {
+            fn load_opts(&mut self, opts: &Options) {
+                self.glyphs = opts.glyphs;
+            }
+
+            fn_on_event_drag!(|t: &Self, buf: &mut Buffer| {
+                let (src, dst) = option!(t.src, t.dst);
+
+                let stroke = Stroke {
+                    glyphs: t.glyphs,
+                    ..Stroke::default()
+                };
+
+                $draw(buf, stroke, Rect::from_corners(src, dst));
+            });
+        }
+    };
 }
 
+shape_tool!(DiamondTool, "Diamond", draw: |buf: &mut Buffer, stroke, r| {
+    buf.draw_diamond(stroke, r)
+});
+
+shape_tool!(HexagonTool, "Hexagon", |r: Rect| {
+    let mid = r.top() + (r.height() - 1) / 2;
+    let inset = min((r.height() - 1) / 2, (r.width() - 1) / 2);
+
+    vec![
+        Vec2::new(r.left() + inset, r.top()),
+        Vec2::new(r.right() - inset, r.top()),
+        Vec2::new(r.right(), mid),
+        Vec2::new(r.right() - inset, r.bottom()),
+        Vec2::new(r.left() + inset, r.bottom()),
+        Vec2::new(r.left(), mid),
+    ]
+});
+
+shape_tool!(ParallelogramTool, "Parallelogram", |r: Rect| {
+    let slant = min(r.height() - 1, (r.width() - 1) / 2);
+
+    vec![
+        Vec2::new(r.left() + slant, r.top()),
+        r.top_right(),
+        Vec2::new(r.right() - slant, r.bottom()),
+        r.bottom_left(),
+    ]
+});
+
+shape_tool!(TrapezoidTool, "Trapezoid", |r: Rect| {
+    let slant = min(r.height() - 1, (r.width() - 1) / 2);
+
+    vec![
+        Vec2::new(r.left() + slant, r.top()),
+        Vec2::new(r.right() - slant, r.top()),
+        r.bottom_right(),
+        r.bottom_left(),
+    ]
+});
+
 #[derive(Clone)]
 pub(crate) struct TextTool {
     src: Option<Vec2>,
+    area: Option<Rect>,
     cursor_active: bool,
     buffer: Vec<Vec<char>>,
     cursor: Vec2,
+    font: Option<Arc<Font>>,
 }
 
 impl Default for TextTool {
     fn default() -> Self {
         Self {
             src: None,
+            area: None,
             cursor_active: false,
             buffer: vec![],
             cursor: Vec2::new(0, 0),
+            font: None,
         }
     }
 }
@@ -233,6 +701,10 @@ simple_display! { TextTool, "Text" }
 
 impl Tool for TextTool {
     fn on_event(&mut self, ctx: &mut EditorCtx<'_>, event: &Event) -> Option<EventResult> {
+        let (width, height) = self
+            .area
+            .map_or((usize::MAX, usize::MAX), |a| (a.width(), a.height()));
+
         let Vec2 { x, y } = &mut self.cursor;
 
         match ctx.relativize(event) {
@@ -242,12 +714,7 @@ impl Tool for TextTool {
                 ..
             } => {
                 if !self.cursor_active {
-                    self.src = Some(position);
-                    self.cursor_active = true;
-                    self.buffer.clear();
-                    self.buffer.push(vec![]);
-                    self.cursor = Vec2::new(0, 0);
-                    ctx.preview(|buf| self.render(buf));
+                    self.start(ctx, position);
                 } else {
                     ctx.clobber(|buf| self.render(buf));
                     self.reset();
@@ -256,6 +723,14 @@ impl Tool for TextTool {
 
             _ if !self.cursor_active => return None,
 
+            // text entered within an area stops at its edges
+            Event::Char(_) if self.buffer[*y].len() >= width => {}
+            Event::Key(Key::Enter) if self.buffer.len() >= height => {}
+            Event::Key(Key::Backspace) | Event::Key(Key::Del)
+                if *x == 0
+                    && *y > 0
+                    && self.buffer[*y - 1].len() + self.buffer[*y].len() > width => {}
+
             Event::Char(c) => {
                 self.buffer[*y].insert(*x, c);
                 *x += 1;
@@ -325,9 +800,55 @@ impl Tool for TextTool {
 }
 
 impl TextTool {
+    /// Start editing text at `pos`.
+    fn start(&mut self, ctx: &mut EditorCtx<'_>, pos: Vec2) {
+        self.src = Some(pos);
+        self.cursor_active = true;
+        self.buffer.clear();
+        self.buffer.push(vec![]);
+        self.cursor = Vec2::new(0, 0);
+        ctx.preview(|buf| self.render(buf));
+    }
+
+    /// Start editing the text inside of `area`, which is replaced by the entered text.
+    fn start_within(&mut self, ctx: &mut EditorCtx<'_>, area: Rect) {
+        let mut lines: Vec<Vec<char>> = vec![];
+
+        ctx.preview(|buf| {
+            for y in area.top()..=area.bottom() {
+                let line: String = (area.left()..=area.right())
+                    .map(|x| buf.getv(Vec2::new(x, y)).unwrap_or(SP))
+                    .collect();
+                lines.push(line.trim_end().chars().collect());
+            }
+        });
+
+        while lines.len() > 1 && lines.last().is_some_and(Vec::is_empty) {
+            lines.pop();
+        }
+
+        self.start(ctx, area.top_left());
+        self.area = Some(area);
+        self.cursor = Vec2::new(lines[lines.len() - 1].len(), lines.len() - 1);
+        self.buffer = lines;
+        ctx.preview(|buf| self.render(buf));
+    }
+
     fn render(&self, buf: &mut Buffer) {
         let src = option!(self.src);
 
+        if let Some(area) = self.area {
+            for y in area.top()..=area.bottom() {
+                for x in area.left()..=area.right() {
+                    buf.setv(true, Vec2::new(x, y), SP);
+                }
+            }
+        }
+
+        if let Some(font) = &self.font {
+            return self.render_banner(buf, src, font);
+        }
+
         for (y, line) in self.buffer.iter().enumerate() {
             for (x, c) in line.iter().enumerate() {
                 let pos = Vec2::new(x, y) + src;
@@ -338,14 +859,47 @@ impl TextTool {
         buf.set_cursor(self.cursor + src);
     }
 
+    fn render_banner(&self, buf: &mut Buffer, src: Vec2, font: &Font) {
+        let h = font.height();
+
+        for (i, line) in self.buffer.iter().enumerate() {
+            for (y, row) in font.render(line).into_iter().enumerate() {
+                // blanks are left alone, so banners can be placed over existing content
+                for (x, c) in row.into_iter().enumerate().filter(|(_, c)| *c != SP) {
+                    buf.setv(true, Vec2::new(x, y + i * h) + src, c);
+                }
+            }
+        }
+
+        let Vec2 { x, y } = self.cursor;
+        let width = font.render(&self.buffer[y][..x])[0].len();
+        buf.set_cursor(Vec2::new(width, y * h + h - 1) + src);
+    }
+
     fn reset(&mut self) {
         self.src = None;
+        self.area = None;
         self.cursor_active = false;
         self.buffer.clear();
         self.cursor = Vec2::new(0, 0);
     }
 }
 
+#[derive(Clone, Default)]
+pub(crate) struct BannerTool(TextTool);
+
+simple_display! { BannerTool, "Banner" }
+
+impl Tool for BannerTool {
+    fn load_opts(&mut self, opts: &Options) {
+        self.0.font = Some(opts.font.clone().unwrap_or_else(Font::block));
+    }
+
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        self.0.on_event(ctx, e)
+    }
+}
+
 #[derive(Copy, Clone, Default)]
 pub(crate) struct EraseTool {
     src: Option<Vec2>,
@@ -364,6 +918,106 @@ impl Tool for EraseTool {
     });
 }
 
+#[derive(Copy, Clone, Default)]
+pub(crate) struct FillTool {
+    src: Option<Vec2>,
+    dst: Option<Vec2>,
+    paint: char,
+}
+
+impl fmt::Display for FillTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Fill: {:?}", self.paint)
+    }
+}
+
+impl Tool for FillTool {
+    fn load_opts(&mut self, opts: &Options) {
+        self.paint = opts.paint;
+    }
+
+    fn_on_event_drag!(|t: &Self, buf: &mut Buffer| {
+        buf.flood_fill(option!(t.dst), t.paint);
+    });
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct BrushTool {
+    points: Vec<Vec2>,
+    paint: char,
+}
+
+impl fmt::Display for BrushTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Brush: {:?}", self.paint)
+    }
+}
+
+impl Tool for BrushTool {
+    fn load_opts(&mut self, opts: &Options) {
+        self.paint = opts.paint;
+    }
+
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        let (pos, event) = mouse_drag!(ctx, e);
+
+        match event {
+            Press(Left) => {
+                self.points = vec![pos];
+                ctx.preview(|buf| buf.stamp(&self.points, self.paint));
+            }
+
+            Hold(Left) if !self.points.is_empty() => {
+                if self.points.last() != Some(&pos) {
+                    self.points.push(pos);
+                }
+                ctx.preview(|buf| buf.stamp(&self.points, self.paint));
+            }
+
+            Release(Left) if !self.points.is_empty() => {
+                if self.points.last() != Some(&pos) {
+                    self.points.push(pos);
+                }
+                ctx.clobber(|buf| buf.stamp(&self.points, self.paint));
+                self.points.clear();
+            }
+
+            _ => return None,
+        }
+
+        CONSUMED
+    }
+}
+
+#[derive(Copy, Clone, Default)]
+pub(crate) struct EyedropperTool;
+
+simple_display! { EyedropperTool, "Eyedropper" }
+
+impl Tool for EyedropperTool {
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        let (pos, event) = mouse_drag!(ctx, e);
+
+        if event != Press(Left) {
+            return None;
+        }
+
+        let mut paint = None;
+        ctx.preview(|buf| paint = buf.getv(pos));
+
+        match paint {
+            Some(c) if !c.is_whitespace() => {
+                ctx.mut_opts(|o| o.paint = c);
+                Some(EventResult::with_cb(move |siv| {
+                    status(siv, format!("paint: {:?}", c))
+                }))
+            }
+
+            _ => CONSUMED,
+        }
+    }
+}
+
 fn visible_cells<'a>(buf: &'a Buffer, cs: (Vec2, Vec2)) -> impl Iterator<Item = Cell> + 'a {
     let area = Rect::from_corners(cs.0, cs.1);
 
@@ -462,3 +1116,297 @@ impl MoveTool {
         }
     }
 }
+
+#[derive(Clone, Default)]
+pub(crate) struct SelectTool {
+    src: Option<Vec2>,
+    dst: Option<Vec2>,
+    paste: Option<Vec<Cell>>,
+    paste_at: Option<Vec2>,
+}
+
+impl fmt::Display for SelectTool {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.paste {
+            Some(_) => write!(f, "Select: Paste"),
+            None => write!(f, "Select"),
+        }
+    }
+}
+
+impl Tool for SelectTool {
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        match e {
+            Event::CtrlChar('c') | Event::CtrlChar('x') if self.paste.is_none() => {
+                let (src, dst) = (self.src?, self.dst?);
+                let tl = Rect::from_corners(src, dst).top_left().signed().map(|v| -v);
+
+                let mut cells = vec![];
+                ctx.preview(|buf| cells = visible_cells(buf, (src, dst)).collect());
+                ctx.copy(cells.iter().map(|cell| cell.translate(tl)).collect());
+
+                if let Event::CtrlChar('x') = e {
+                    ctx.clobber(|buf| {
+                        for cell in cells {
+                            buf.setv(true, cell.pos(), SP);
+                        }
+                    });
+                    self.src = None;
+                    self.dst = None;
+                }
+
+                ctx.preview(|buf| self.render(buf));
+                return CONSUMED;
+            }
+
+            Event::CtrlChar('v') => {
+                let at = self
+                    .src
+                    .zip(self.dst)
+                    .map(|(src, dst)| Rect::from_corners(src, dst));
+                self.paste_at = ctx.pointer().or(at.map(|r| r.top_left()));
+                self.paste = Some(ctx.paste());
+                self.src = None;
+                self.dst = None;
+                ctx.preview(|buf| self.render(buf));
+                return CONSUMED;
+            }
+
+            Event::Key(Key::Esc) if self.paste.is_some() => {
+                self.paste = None;
+                self.paste_at = None;
+                ctx.preview(|_| ());
+                return CONSUMED;
+            }
+
+            Event::Char('|') => return self.transform(ctx, Transform::FlipH),
+            Event::Char('-') => return self.transform(ctx, Transform::FlipV),
+            Event::Char('R') => return self.transform(ctx, Transform::Rotate),
+
+            _ => {}
+        }
+
+        let (pos, event) = mouse_drag!(ctx, e);
+
+        match event {
+            Press(Left) | Hold(Left) if self.paste.is_some() => {
+                self.paste_at = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Release(Left) if self.paste.is_some() => {
+                self.paste_at = Some(pos);
+                ctx.clobber(|buf| self.render(buf));
+                self.paste = None;
+                self.paste_at = None;
+            }
+
+            Press(Left) => {
+                self.src = Some(pos);
+                self.dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Hold(Left) | Release(Left) => {
+                self.dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            _ => return None,
+        }
+
+        CONSUMED
+    }
+}
+
+impl SelectTool {
+    /// Apply `t` to the content being pasted, or else the selected content.
+    fn transform(&mut self, ctx: &mut EditorCtx<'_>, t: Transform) -> Option<EventResult> {
+        if let Some(cells) = self.paste.as_mut() {
+            let size = cells
+                .iter()
+                .fold(Vec2::zero(), |size, cell| size.or_max(cell.pos() + (1, 1)));
+
+            *cells = transform_cells(cells, t, size);
+
+            ctx.preview(|buf| self.render(buf));
+            return CONSUMED;
+        }
+
+        let (src, dst) = (self.src?, self.dst?);
+        let area = Rect::from_corners(src, dst);
+        let (tl, size) = (area.top_left(), area.size());
+
+        ctx.clobber(|buf| {
+            let cells: Vec<_> = visible_cells(buf, (src, dst)).collect();
+
+            for cell in cells.iter() {
+                buf.setv(true, cell.pos(), SP);
+            }
+
+            let cells: Vec<_> = (cells.into_iter())
+                .map(|cell| cell.translate(tl.signed().map(|v| -v)))
+                .collect();
+
+            for cell in transform_cells(&cells, t, size)
+                .into_iter()
+                .map(|cell| cell.translate(tl.signed()))
+            {
+                buf.setv(true, cell.pos(), cell.c());
+            }
+        });
+
+        if let Transform::Rotate = t {
+            self.dst = Some(tl + (size.y - 1, size.x - 1));
+            self.src = Some(tl);
+        }
+
+        ctx.preview(|buf| self.render(buf));
+        CONSUMED
+    }
+
+    fn render(&self, buf: &mut Buffer) {
+        if let (Some(cells), Some(at)) = (&self.paste, self.paste_at) {
+            for cell in cells.iter().map(|cell| cell.translate(at.signed())) {
+                buf.setv(true, cell.pos(), cell.c());
+            }
+            return;
+        }
+
+        // highlight the selected content
+        let (src, dst) = option!(self.src, self.dst);
+        for cell in visible_cells(buf, (src, dst)).collect::<Vec<_>>() {
+            buf.setv(true, cell.pos(), cell.c());
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct ResizeTool {
+    target: Option<(Rect, Stroke, Vec<usize>)>,
+    grab_src: Option<Vec2>,
+    grab_dst: Option<Vec2>,
+}
+
+simple_display! { ResizeTool, "Resize" }
+
+impl Tool for ResizeTool {
+    fn on_event(&mut self, ctx: &mut EditorCtx<'_>, e: &Event) -> Option<EventResult> {
+        let (pos, event) = mouse_drag!(ctx, e);
+
+        match event {
+            Press(Left) => {
+                self.target = None;
+                ctx.preview(|buf| self.target = buf.find_box(pos));
+                self.grab_src = Some(pos);
+                self.grab_dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Hold(Left) if self.target.is_some() => {
+                self.grab_dst = Some(pos);
+                ctx.preview(|buf| self.render(buf));
+            }
+
+            Release(Left) if self.target.is_some() => {
+                self.grab_dst = Some(pos);
+                ctx.clobber(|buf| self.render(buf));
+                self.target = None;
+                self.grab_src = None;
+                self.grab_dst = None;
+            }
+
+            _ => return None,
+        }
+
+        CONSUMED
+    }
+}
+
+impl ResizeTool {
+    fn render(&self, buf: &mut Buffer) {
+        let (r, stroke, dividers) = option!(self.target.as_ref());
+        let (grab_src, grab_dst) = option!(self.grab_src, self.grab_dst);
+        let (r, stroke) = (*r, *stroke);
+
+        let resized = resize_box(buf, r, dividers, grab_src, grab_dst);
+
+        for pos in box_edges(r) {
+            buf.setv(true, pos, SP);
+        }
+        for &y in dividers {
+            for x in r.left()..=r.right() {
+                buf.setv(true, Vec2::new(x, y), SP);
+            }
+        }
+
+        buf.draw_box(stroke, resized);
+
+        for &y in dividers {
+            let (l, r) = (resized.left(), resized.right());
+            buf.draw_line(stroke, Vec2::new(l, y), Vec2::new(r, y));
+        }
+    }
+}
+
+/// Returns the box `r` with whichever edges that `grab_src` is on moved by the distance
+/// from `grab_src` to `grab_dst`. Edges stop short of any content inside of the box, and
+/// of the rows of any `dividers` between its compartments.
+fn resize_box(buf: &Buffer, r: Rect, dividers: &[usize], grab_src: Vec2, grab_dst: Vec2) -> Rect {
+    let delta = grab_dst.signed() - grab_src.signed();
+
+    let inner = Rect::from_corners(r.top_left() + (1, 1), r.bottom_right() - (1, 1));
+    let content = (r.width() > 2 && r.height() > 2)
+        .then(|| visible_cells(buf, (inner.top_left(), inner.bottom_right())))
+        .into_iter()
+        .flatten()
+        .filter(|cell| !dividers.contains(&cell.pos().y))
+        .map(|cell| Rect::from_size(cell.pos(), (1, 1)))
+        .reduce(|a, b| a.expanded_to(b));
+
+    // the innermost that each edge may go, keeping at least 1 cell between opposing edges
+    // and any content inside
+    let (min_r, mut min_b) = content.map_or((r.left() + 1, r.top() + 1), |c| {
+        (c.right() + 1, c.bottom() + 1)
+    });
+    let (max_l, mut max_t) = content.map_or((r.right() - 1, r.bottom() - 1), |c| {
+        (c.left() - 1, c.top() - 1)
+    });
+
+    // the outermost dividers can sit right next to the edges, pinning them in place
+    if let (Some(&first), Some(&last)) = (dividers.first(), dividers.last()) {
+        min_b = max(min_b, last + 1);
+        max_t = min(max_t, first.saturating_sub(1));
+    }
+
+    let shift = |v: usize, d: isize| (v as isize + d).max(0) as usize;
+
+    let (mut tl, mut br) = (r.top_left(), r.bottom_right());
+
+    if grab_src.x == r.left() {
+        tl.x = min(shift(tl.x, delta.x), max_l);
+    } else if grab_src.x == r.right() {
+        br.x = max(shift(br.x, delta.x), min_r);
+    }
+
+    if grab_src.y == r.top() {
+        tl.y = min(shift(tl.y, delta.y), max_t);
+    } else if grab_src.y == r.bottom() {
+        br.y = max(shift(br.y, delta.y), min_b);
+    }
+
+    Rect::from_corners(tl, br)
+}
+
+/// Returns every position along the edges of `r`.
+fn box_edges(r: Rect) -> impl Iterator<Item = Vec2> {
+    let horizontal = (r.left()..=r.right()).flat_map(move |x| {
+        iter::once(Vec2::new(x, r.top())).chain(iter::once(Vec2::new(x, r.bottom())))
+    });
+
+    let vertical = (r.top()..=r.bottom()).flat_map(move |y| {
+        iter::once(Vec2::new(r.left(), y)).chain(iter::once(Vec2::new(r.right(), y)))
+    });
+
+    horizontal.chain(vertical)
+}
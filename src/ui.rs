@@ -3,16 +3,16 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 use super::{
-    editor::{Editor, EditorView},
+    editor::{Editor, EditorView, STATUS_TTL},
     EDITOR_ID,
 };
 use cursive::{
     align::HAlign,
-    view::{Margins, Nameable},
-    views::{Dialog, EditView, ScrollView, TextView},
+    view::{Margins, Nameable, Resizable},
+    views::{Dialog, DummyView, EditView, LinearLayout, ScrollView, SelectView, TextView},
     Cursive,
 };
-use std::rc::Rc;
+use std::{rc::Rc, sync::mpsc::Sender, time::Instant};
 
 const NO_MARGIN: Margins = Margins {
     left: 0,
@@ -23,10 +23,10 @@ const NO_MARGIN: Margins = Margins {
 
 /// Run `f` if the editor's buffer has not been modified since the last save, or if user
 /// has confirmed that they're ok with discarding unsaved changes.
-pub(super) fn with_checked_editor<T, F: 'static>(siv: &mut Cursive, title: T, f: F)
+pub(super) fn with_checked_editor<T, F>(siv: &mut Cursive, title: T, f: F)
 where
     T: Into<String>,
-    F: Fn(&mut Cursive),
+    F: Fn(&mut Cursive) + 'static,
 {
     if with_editor(siv, Editor::is_dirty) {
         display_yesno(siv, title, "Discard unsaved changes?", f);
@@ -61,11 +61,11 @@ const POPUP_ID: &str = "generic_popup";
 
 /// Display a "Yes / No" prompt with the provided `title`, running `yes` iff "Yes" is
 /// pressed. Defaults to "No".
-pub(super) fn display_yesno<T, C, F: 'static>(siv: &mut Cursive, title: T, content: C, yes: F)
+pub(super) fn display_yesno<T, C, F>(siv: &mut Cursive, title: T, content: C, yes: F)
 where
     T: Into<String>,
     C: Into<String>,
-    F: Fn(&mut Cursive),
+    F: Fn(&mut Cursive) + 'static,
 {
     if siv.find_name::<Dialog>(POPUP_ID).is_some() {
         return;
@@ -87,10 +87,10 @@ where
 
 /// Display a single line input form, passing the submitted content into the provided
 /// callback `form`.
-pub(super) fn display_form<T, F: 'static>(siv: &mut Cursive, title: T, form: F)
+pub(super) fn display_form<T, F>(siv: &mut Cursive, title: T, form: F)
 where
     T: Into<String>,
-    F: Fn(&mut Cursive, &'static str, &str),
+    F: Fn(&mut Cursive, &'static str, &str) + 'static,
 {
     if siv.find_name::<Dialog>(POPUP_ID).is_some() {
         return;
@@ -122,6 +122,58 @@ where
     siv.add_layer(popup);
 }
 
+/// Display a list of `choices` to pick a character from, along with a field to enter any
+/// other character, passing the picked one into the provided callback `pick`.
+pub(super) fn display_char_picker<T, F>(
+    siv: &mut Cursive,
+    title: T,
+    choices: &[(char, &str)],
+    pick: F,
+) where
+    T: Into<String>,
+    F: Fn(&mut Cursive, char) + 'static,
+{
+    if siv.find_name::<Dialog>(POPUP_ID).is_some() {
+        return;
+    }
+
+    let pick = Rc::new(move |siv: &mut Cursive, c: char| {
+        siv.pop_layer();
+        pick(siv, c);
+    });
+
+    let pick_other = Rc::clone(&pick);
+
+    let mut select = SelectView::new().on_submit(move |siv, c: &char| pick(siv, *c));
+    for (c, name) in choices {
+        select.add_item(format!("{}  {}", c, name), *c);
+    }
+
+    let other = EditView::new()
+        .max_content_width(1)
+        .on_submit(move |siv, input| {
+            if let Some(c) = input.chars().next() {
+                pick_other(siv, c);
+            }
+        });
+
+    let content = LinearLayout::vertical()
+        .child(ScrollView::new(select).max_height(12))
+        .child(DummyView)
+        .child(
+            LinearLayout::horizontal()
+                .child(TextView::new("Other: "))
+                .child(other.fixed_width(3)),
+        );
+
+    let popup = Dialog::around(content)
+        .title(title)
+        .dismiss_button("Cancel")
+        .with_name(POPUP_ID);
+
+    siv.add_layer(popup);
+}
+
 /// Display a notification dialog.
 pub(super) fn notify<T, C>(siv: &mut Cursive, title: T, content: C)
 where
@@ -141,6 +193,19 @@ where
     );
 }
 
+/// Display a transient status message in the modeline.
+pub(super) fn status<S: Into<String>>(siv: &mut Cursive, msg: S) {
+    with_editor_mut(siv, |e| e.set_status(msg));
+
+    if let Some(StatusTimer(timer)) = siv.user_data::<StatusTimer>() {
+        let _ = timer.send(Instant::now() + STATUS_TTL);
+    }
+}
+
+/// Tells the timer thread when the current status message expires, so that it gets
+/// cleared from the modeline then.
+pub(super) struct StatusTimer(pub(super) Sender<Instant>);
+
 /// Display a unique notification dialog. No two dialogs with the same `unique_id` will
 /// ever be shown at the same time.
 pub(super) fn notify_unique<T, C>(siv: &mut Cursive, unique_id: &'static str, title: T, content: C)